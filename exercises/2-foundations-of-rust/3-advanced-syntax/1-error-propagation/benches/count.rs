@@ -0,0 +1,39 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use error_propagating::{count, count_parallel};
+
+/// Generates `len` bytes of something that looks like a log, with some non-ASCII text in it
+fn log(len: usize) -> Vec<u8> {
+    let lines = [
+        "2024-03-01T12:00:00Z INFO  request handled\tpath=/index.html status=200 time=3ms\n",
+        "2024-03-01T12:00:01Z WARN  slow response\tpath=/search?q=größe status=200 time=950ms\n",
+        "2024-03-01T12:00:02Z ERROR upstream failed\tpath=/api/用户 status=502 time=30s\n",
+    ];
+    lines
+        .iter()
+        .cycle()
+        .flat_map(|line| line.bytes())
+        .take(len)
+        .collect()
+}
+
+/// Counts a log with [count], which reads and counts it on one thread, and with
+/// [count_parallel], which counts chunks of it on all threads
+fn bench_sequential_vs_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sequential_vs_parallel");
+    group.sample_size(10);
+    for len in [1 << 20, 64 << 20] {
+        let log = log(len);
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::new("sequential", len), &log, |b, log| {
+            b.iter(|| count(&log[..]).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", len), &log, |b, log| {
+            b.iter(|| count_parallel(&log[..]).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sequential_vs_parallel);
+criterion_main!(benches);
@@ -0,0 +1,537 @@
+//! The counting behind the `wc` in `src/main.rs`. It works on raw bytes rather than lines of text,
+//! so that every byte is counted, whether or not the input is valid UTF-8:
+//!
+//! - bytes are all bytes, so the count matches the size of a file;
+//! - lines are `\n` bytes, so a `\r\n` counts as one line, and a last line without a newline as none;
+//! - words are maximal runs of bytes that are not ASCII whitespace (space, `\t`, `\n`, `\v`, `\f` and `\r`),
+//!   regardless of the locale. Unlike GNU `wc`, a run of unprintable bytes counts as a word as well;
+//! - characters are decoded as UTF-8, where each invalid sequence counts as one replacement character,
+//!   like [`String::from_utf8_lossy`] does.
+//!
+//! The input is fed to a [`Counter`] in chunks, which may split a word or a UTF-8 character in two.
+//! [`count_parallel`] counts the chunks of a large input on all cores instead, and merges the counters afterwards.
+
+use std::{
+    io::{self, ErrorKind, Read},
+    str,
+};
+
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub lines: usize,
+    pub words: usize,
+    pub chars: usize,
+    pub bytes: usize,
+    /// The width of the widest line, where a tab advances to the next multiple of 8
+    pub max_line_length: usize,
+}
+
+impl Counts {
+    /// Adds the counts of another file to a total
+    pub fn add(&mut self, other: &Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+    }
+}
+
+/// Counts an input that is fed to it in chunks of any size. Counters of consecutive parts of
+/// the input can also be [merged](Counter::merge), so that the parts can be counted in parallel.
+#[derive(Debug, Clone, Default)]
+pub struct Counter {
+    /// The counts so far, where `max_line_length` is only that of the lines in between
+    /// the first line break and the current line
+    counts: Counts,
+    /// Whether the first byte is part of a word, which may have started before this part of the input
+    starts_in_word: bool,
+    /// Whether the last byte so far is part of a word
+    in_word: bool,
+    /// Everything up to the first line break, if there has been one. Where it starts is only known
+    /// once the counter is merged with the one of the input before it.
+    first_line: Option<Segment>,
+    /// Everything since the last line break
+    line: Segment,
+    /// The start of a UTF-8 character that was cut off at the end of the last chunk
+    pending: Vec<u8>,
+}
+
+impl Counter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, mut chunk: &[u8]) {
+        let counts = &mut self.counts;
+        if counts.bytes == 0 && !chunk.is_empty() {
+            self.starts_in_word = !is_space(chunk[0]);
+        }
+        counts.bytes += chunk.len();
+        for &byte in chunk {
+            if byte == b'\n' {
+                counts.lines += 1;
+            }
+            if is_space(byte) {
+                self.in_word = false;
+            } else if !self.in_word {
+                self.in_word = true;
+                counts.words += 1;
+            }
+        }
+
+        // Complete the character from the last chunk, which takes at most 3 more bytes
+        while !self.pending.is_empty() && !chunk.is_empty() {
+            self.pending.push(chunk[0]);
+            chunk = &chunk[1..];
+            if !is_incomplete(&self.pending) {
+                let pending = std::mem::take(&mut self.pending);
+                self.decode(&pending);
+            }
+        }
+        self.decode(chunk);
+    }
+
+    /// Combines the counter of a part of the input with that of the part right after it.
+    /// `next` must start at a [`char_boundary`], which a split at a line break always is.
+    pub fn merge(mut self, next: Counter) -> Counter {
+        if next.counts.bytes == 0 {
+            return self;
+        }
+        if self.counts.bytes == 0 {
+            return next;
+        }
+        // A character that is cut off at the end of `self` is not completed by `next`
+        self.flush_pending();
+
+        let mut counts = self.counts;
+        counts.add(&next.counts);
+        // A word that runs across the boundary is counted by both
+        if self.in_word && next.starts_in_word {
+            counts.words -= 1;
+        }
+        let (first_line, line) = match (self.first_line, next.first_line) {
+            (first_line, None) => (first_line, self.line.then(next.line)),
+            (None, Some(next_first_line)) => (Some(self.line.then(next_first_line)), next.line),
+            (Some(first_line), Some(next_first_line)) => {
+                // The line that runs across the boundary is now complete
+                let width = self.line.then(next_first_line).width();
+                counts.max_line_length = counts.max_line_length.max(width);
+                (Some(first_line), next.line)
+            }
+        };
+        Counter {
+            counts,
+            starts_in_word: self.starts_in_word,
+            in_word: next.in_word,
+            first_line,
+            line,
+            pending: next.pending,
+        }
+    }
+
+    /// Returns the counts of everything fed to the counter so far
+    pub fn finish(mut self) -> Counts {
+        // The input may end in the middle of a character
+        self.flush_pending();
+        let first_line = self.first_line.unwrap_or_default();
+        let counts = &mut self.counts;
+        counts.max_line_length = counts
+            .max_line_length
+            .max(first_line.width())
+            .max(self.line.width());
+        self.counts
+    }
+
+    /// Counts the characters in `bytes`, except for a character that is cut off at the end,
+    /// which is kept until the next chunk
+    fn decode(&mut self, bytes: &[u8]) {
+        let mut pieces = bytes.utf8_chunks().peekable();
+        while let Some(piece) = pieces.next() {
+            self.chars(piece.valid());
+            let invalid = piece.invalid();
+            if invalid.is_empty() {
+                continue;
+            }
+            if pieces.peek().is_none() && is_incomplete(invalid) {
+                self.pending.extend_from_slice(invalid);
+            } else {
+                self.chars("\u{FFFD}");
+            }
+        }
+    }
+
+    /// Counts a character that was cut off as a replacement character
+    fn flush_pending(&mut self) {
+        if !self.pending.is_empty() {
+            self.pending.clear();
+            self.chars("\u{FFFD}");
+        }
+    }
+
+    fn chars(&mut self, text: &str) {
+        for c in text.chars() {
+            self.counts.chars += 1;
+            match c {
+                // These return to the start of the line
+                '\n' | '\r' | '\x0c' => {
+                    let line = std::mem::take(&mut self.line);
+                    if self.first_line.is_none() {
+                        self.first_line = Some(line);
+                    } else {
+                        let max_line_length = &mut self.counts.max_line_length;
+                        *max_line_length = (*max_line_length).max(line.width());
+                    }
+                }
+                c => self.line.push(c),
+            }
+        }
+    }
+}
+
+/// A part of a line. Its width depends on the column it starts in, because a tab
+/// advances to the next multiple of 8, but only up to its first tab.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Segment {
+    /// The width before the first tab
+    before_tab: usize,
+    /// The width after the first tab, counted from the multiple of 8 the tab advanced to
+    after_tab: Option<usize>,
+}
+
+impl Segment {
+    fn push(&mut self, c: char) {
+        match (c, &mut self.after_tab) {
+            ('\t', None) => self.after_tab = Some(0),
+            ('\t', Some(width)) => *width += 8 - *width % 8,
+            // Other control characters take no space
+            (c, _) if c.is_control() => {}
+            (_, None) => self.before_tab += 1,
+            (_, Some(width)) => *width += 1,
+        }
+    }
+
+    /// Returns the column the segment ends in, if it starts in column `start`
+    fn end(self, start: usize) -> usize {
+        let column = start + self.before_tab;
+        match self.after_tab {
+            None => column,
+            Some(width) => column - column % 8 + 8 + width,
+        }
+    }
+
+    /// The width of a segment at the start of a line
+    fn width(self) -> usize {
+        self.end(0)
+    }
+
+    /// Returns the segment of `self` followed by `next`
+    fn then(self, next: Segment) -> Segment {
+        match self.after_tab {
+            None => Segment {
+                before_tab: self.before_tab + next.before_tab,
+                after_tab: next.after_tab,
+            },
+            // `self` ends at a multiple of 8 plus `width`, and only that remainder matters to `next`
+            Some(width) => Segment {
+                before_tab: self.before_tab,
+                after_tab: Some(next.end(width)),
+            },
+        }
+    }
+}
+
+/// Returns the position at or a few bytes before `at` where a UTF-8 character, or an invalid
+/// sequence, starts. The bytes before and after it can be counted separately and then merged.
+pub fn char_boundary(bytes: &[u8], at: usize) -> usize {
+    let is_continuation = |byte: u8| byte & 0b1100_0000 == 0b1000_0000;
+    // A byte after 3 continuation bytes starts something new, even if it is a continuation byte,
+    // because a character has at most 3 of them
+    (at.saturating_sub(3)..=at)
+        .rev()
+        .find(|&i| i == bytes.len() || !is_continuation(bytes[i]))
+        .unwrap_or(at)
+}
+
+/// The whitespace of the C locale
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+/// Returns whether `bytes` is the start of a UTF-8 character that more bytes could complete
+fn is_incomplete(bytes: &[u8]) -> bool {
+    matches!(str::from_utf8(bytes), Err(e) if e.error_len().is_none())
+}
+
+/// Counts everything `reader` produces. If reading fails halfway, the counts so far
+/// are returned along with the error.
+pub fn count(mut reader: impl Read) -> Result<Counts, (Counts, io::Error)> {
+    let mut counter = Counter::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(counter.finish()),
+            Ok(len) => counter.update(&buf[..len]),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err((counter.finish(), e)),
+        }
+    }
+}
+
+/// The number of bytes [`count_parallel`] reads at a time
+const BLOCK_SIZE: usize = 16 * 1024 * 1024;
+
+/// The number of bytes each thread counts at a time
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Counts everything `reader` produces, like [`count`], but reads it in large blocks,
+/// and counts the chunks of each block in parallel. That pays off for large inputs,
+/// such as multi-gigabyte logs.
+pub fn count_parallel(reader: impl Read) -> Result<Counts, (Counts, io::Error)> {
+    count_blocks(reader, BLOCK_SIZE, CHUNK_SIZE)
+}
+
+fn count_blocks(
+    mut reader: impl Read,
+    block_size: usize,
+    chunk_size: usize,
+) -> Result<Counts, (Counts, io::Error)> {
+    // A block ends before the last character in it, which may be cut off,
+    // so each block needs to have more than the at most 4 bytes of a character
+    assert!(block_size > 4 && chunk_size > 4);
+    let mut counter = Counter::new();
+    // Starts with the bytes left over from the last block. It grows as it is filled,
+    // so that a small input doesn't need a buffer of a whole block.
+    let mut block = Vec::new();
+    loop {
+        let wanted = (block_size - block.len()) as u64;
+        let result = reader.by_ref().take(wanted).read_to_end(&mut block);
+        if block.len() < block_size || result.is_err() {
+            // This is the last block: the end of the input, or an error
+            counter = counter.merge(count_chunks(&block, chunk_size));
+            return match result {
+                Ok(_) => Ok(counter.finish()),
+                Err(e) => Err((counter.finish(), e)),
+            };
+        }
+        let end = char_boundary(&block, block.len() - 1);
+        counter = counter.merge(count_chunks(&block[..end], chunk_size));
+        block.drain(..end);
+    }
+}
+
+/// Counts `bytes` in chunks of about `chunk_size` bytes, in parallel
+fn count_chunks(bytes: &[u8], chunk_size: usize) -> Counter {
+    let mut bounds = vec![0];
+    let mut at = chunk_size;
+    while at < bytes.len() {
+        bounds.push(char_boundary(bytes, at));
+        at += chunk_size;
+    }
+    bounds.push(bytes.len());
+    bounds
+        .par_windows(2)
+        .map(|bounds| {
+            let mut counter = Counter::new();
+            counter.update(&bytes[bounds[0]..bounds[1]]);
+            counter
+        })
+        .reduce(Counter::new, Counter::merge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_bytes(bytes: &[u8]) -> Counts {
+        count(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_count() {
+        assert_eq!(
+            count_bytes(b"hello world\nfoo\tbar\n"),
+            Counts {
+                lines: 2,
+                words: 4,
+                chars: 20,
+                bytes: 20,
+                max_line_length: 11,
+            }
+        );
+
+        // A last line without a newline is not counted as a line, but its words and bytes are
+        let counts = count_bytes(b"one\ntwo  three");
+        assert_eq!((counts.lines, counts.words, counts.bytes), (1, 3, 14));
+        assert_eq!(count_bytes(b""), Counts::default());
+        assert_eq!(count_bytes(b"\n\n").lines, 2);
+
+        // Characters are not bytes
+        let counts = count_bytes("héllo wörld\n".as_bytes());
+        assert_eq!((counts.chars, counts.bytes), (12, 14));
+    }
+
+    #[test]
+    fn test_crlf() {
+        let counts = count_bytes(b"one two\r\nthree\r\n");
+        assert_eq!(
+            counts,
+            Counts {
+                lines: 2,
+                words: 3,
+                chars: 16,
+                bytes: 16,
+                max_line_length: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn test_whitespace() {
+        // Vertical tabs and form feeds separate words too, but non-breaking spaces don't
+        assert_eq!(count_bytes(b"a\x0bb\x0cc\rd").words, 4);
+        assert_eq!(count_bytes("a\u{a0}b".as_bytes()).words, 1);
+        assert_eq!(count_bytes(b"\x00\x01 \x02").words, 2);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // Latin-1, where `é` is a single byte
+        let counts = count_bytes(b"caf\xe9 au lait\n");
+        assert_eq!((counts.words, counts.chars, counts.bytes), (3, 13, 13));
+        // An invalid byte, and a character that is cut off, count as one replacement character each
+        assert_eq!(count_bytes(b"a\xffb").chars, 3);
+        assert_eq!(count_bytes(b"a\xe2\x82").chars, 2);
+        assert_eq!(count_bytes(b"\xe2\x82a").chars, 2);
+        assert_eq!(count_bytes(b"\xf0\x9f\x92\x96").chars, 1);
+    }
+
+    #[test]
+    fn test_line_length() {
+        let max_line_length = |text: &str| count_bytes(text.as_bytes()).max_line_length;
+        assert_eq!(max_line_length(""), 0);
+        assert_eq!(max_line_length("abc\n"), 3);
+        assert_eq!(max_line_length("\tx"), 9);
+        assert_eq!(max_line_length("abcdefgh\tx"), 17);
+        assert_eq!(max_line_length("abc\tx"), 9);
+        assert_eq!(max_line_length("long line\rshort"), 9);
+        assert_eq!(max_line_length("é\x07\nab"), 2);
+    }
+
+    #[test]
+    fn test_chunks() {
+        // However the input is split up, the counts are the same
+        let text = [
+            "Ünïcödé wörds\r\n\tsplit 💖 up\n".as_bytes(),
+            b"\xff\xfe; and more words\n\xe2\x82 \xf0\x9f",
+        ]
+        .concat();
+        let expected = count_bytes(&text);
+        assert_eq!((expected.words, expected.chars), (11, 49));
+        for size in 1..text.len() {
+            let mut counter = Counter::new();
+            for chunk in text.chunks(size) {
+                counter.update(chunk);
+            }
+            assert_eq!(counter.finish(), expected, "chunks of {size} bytes");
+        }
+        for split in 0..text.len() {
+            let mut counter = Counter::new();
+            counter.update(&text[..split]);
+            counter.update(&[]);
+            counter.update(&text[split..]);
+            assert_eq!(counter.finish(), expected, "split at {split}");
+        }
+    }
+
+    /// Generates bytes that are mostly text, with all kinds of whitespace, multi-byte characters,
+    /// and invalid or cut-off UTF-8 in between
+    fn mixed_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let pieces: [&[u8]; 12] = [
+            b"word",
+            b" ",
+            b"\t",
+            b"\r\n",
+            b"\n",
+            b"\x0b\x0c",
+            "größe".as_bytes(),
+            "💖".as_bytes(),
+            b"\xff",
+            b"\xe2\x82",
+            b"\x80\x80\x80\x80\x80",
+            b"\x07",
+        ];
+        let mut state = seed;
+        let mut bytes = Vec::new();
+        while bytes.len() < len {
+            // A linear congruential generator is random enough here
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            bytes.extend_from_slice(pieces[(state >> 33) as usize % pieces.len()]);
+        }
+        bytes.truncate(len);
+        bytes
+    }
+
+    #[test]
+    fn test_char_boundary() {
+        let text = "a💖b".as_bytes();
+        assert_eq!(char_boundary(text, 0), 0);
+        assert_eq!(char_boundary(text, 1), 1);
+        assert_eq!(char_boundary(text, 3), 1);
+        assert_eq!(char_boundary(text, 5), 5);
+        assert_eq!(char_boundary(text, 6), 6);
+        // A run of stray continuation bytes can be split after any 3 of them
+        let bytes = b"a\x80\x80\x80\x80\x80";
+        assert_eq!(char_boundary(bytes, 3), 0);
+        assert_eq!(char_boundary(bytes, 4), 4);
+        assert_eq!(char_boundary(bytes, 5), 5);
+    }
+
+    #[test]
+    fn test_merge() {
+        for seed in 0..20 {
+            let bytes = mixed_bytes(200, seed);
+            let expected = count_bytes(&bytes);
+            for at in 0..=bytes.len() {
+                let split = char_boundary(&bytes, at);
+                let mut counters = [Counter::new(), Counter::new()];
+                counters[0].update(&bytes[..split]);
+                counters[1].update(&bytes[split..]);
+                let [first, second] = counters;
+                assert_eq!(
+                    first.merge(second).finish(),
+                    expected,
+                    "{bytes:?} split at {split}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_parallel() {
+        for seed in 0..10 {
+            let bytes = mixed_bytes(500, seed);
+            let expected = count_bytes(&bytes);
+            assert_eq!(count_parallel(&bytes[..]).unwrap(), expected);
+            for block_size in [5, 6, 7, 13, 64, 499, 500, 501] {
+                for chunk_size in [5, 8, 17, 100] {
+                    assert_eq!(
+                        count_blocks(&bytes[..], block_size, chunk_size).unwrap(),
+                        expected,
+                        "blocks of {block_size} and chunks of {chunk_size} bytes"
+                    );
+                }
+            }
+        }
+        // The long lines and words of a minified file run across many chunks
+        let bytes = "x\ty".repeat(1000).into_bytes();
+        assert_eq!(
+            count_blocks(&bytes[..], 64, 7).unwrap(),
+            count_bytes(&bytes)
+        );
+    }
+}
@@ -1,50 +1,316 @@
-// This is a very basic rust implementation that approximates the UNIX 'wc' program for text-files; for example, the file you are reading now
-// has 50 lines, 303 words, and 2076 bytes, which you can verify by running cargo run -- src/main.rs
+// An implementation of the UNIX `wc` program, which counts the lines, words, characters and bytes of files.
+// It takes the options -l, -w, -m, -c and -L to choose the counts, and any number of files, where `-` or no file
+// at all means standard input. You can compare it with the real thing by running `cargo run -- src/main.rs`
+// and `wc src/main.rs`. The output is aligned like that of GNU `wc`:
 //
-// However, reading a particular line in a file can fail (for example, if it is not a valid UTF-8 file); this will be very rare, and the code
-// just uses unwrap() to convert a Result<String,Error> into a String. If this doesn't succeed, the program panics.
-// And of course, opening a file can fail for obvious reasons.
+//     $ printf 'hello world\n' | cargo run -q -- -lw - missing.txt
+//           1       2 -
+//     wc: missing.txt: No such file or directory (os error 2)
+//           1       2 total
 //
-// You can trigger this behaviour by running this program on something that is
-// not a text file, e.g. cargo run -- /bin/bash or cargo run -- c:\windows\system32\cmd.exe
+// Opening or reading a file can fail, and instead of panicking, such an error is returned as a `Result`:
+// `count` in src/lib.rs returns a read error along with the counts up to that point, and `count_file` propagates
+// an error opening the file with `?`. `main` reports the error with `eprintln!`, continues with the next file,
+// and exits with a non-zero status at the end. An invalid command line is reported as a `UsageError`.
 //
-// Your tasks:
-// 1) change the functions read_lines and count_bytes_and_lines so they return a Result<TYPE, io::Error>, and make them propagate errors.
-//
-// 2) handle these errors in main, reporting any error that occurred in main() using eprintln!
+// The counting itself is in src/lib.rs. It reads raw bytes instead of lines of text, so a file that is not valid UTF-8
+// can be counted as well, and the number of bytes always matches the size of the file.
+// With --parallel, each file is read in large blocks, of which the chunks are counted on all cores.
+// `cargo bench` compares the throughput of both.
+
+use std::{
+    env, fmt,
+    fs::{self, File},
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use error_propagating::{count, count_parallel, Counts};
+
+const USAGE: &str = "usage: wc [-clmwL] [--parallel] [file ...]";
+
+/// Which counts to print, and how to count them. Whichever counts are selected,
+/// they are printed in the order of the fields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Options {
+    lines: bool,
+    words: bool,
+    chars: bool,
+    bytes: bool,
+    max_line_length: bool,
+    /// Count each file in parallel chunks, which is faster for large files
+    parallel: bool,
+}
+
+impl Options {
+    /// Without any options, `wc` prints the lines, words and bytes
+    const DEFAULT: Options = Options {
+        lines: true,
+        words: true,
+        chars: false,
+        bytes: true,
+        max_line_length: false,
+        parallel: false,
+    };
+
+    /// Returns the number of counts to print
+    fn count(&self) -> usize {
+        [
+            self.lines,
+            self.words,
+            self.chars,
+            self.bytes,
+            self.max_line_length,
+        ]
+        .into_iter()
+        .filter(|&selected| selected)
+        .count()
+    }
+}
+
+/// The reasons the command line can be rejected
+#[derive(Debug, PartialEq, Eq)]
+enum UsageError {
+    InvalidOption(char),
+    UnrecognizedOption(String),
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsageError::InvalidOption(c) => write!(f, "invalid option -- '{c}'"),
+            UsageError::UnrecognizedOption(option) => write!(f, "unrecognized option '{option}'"),
+        }
+    }
+}
+
+/// Splits the arguments (without the program name) into the options and the files.
+/// `--` ends the options, so that a file whose name starts with `-` can be counted.
+fn parse_args(args: &[String]) -> Result<(Options, Vec<String>), UsageError> {
+    let mut options = Options::default();
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => {
+                files.extend(args.by_ref().cloned());
+            }
+            "--lines" => options.lines = true,
+            "--words" => options.words = true,
+            "--chars" => options.chars = true,
+            "--bytes" => options.bytes = true,
+            "--max-line-length" => options.max_line_length = true,
+            "--parallel" => options.parallel = true,
+            long if long.starts_with("--") => {
+                return Err(UsageError::UnrecognizedOption(long.to_string()))
+            }
+            // `-` by itself is standard input, not an option
+            short if short.starts_with('-') && short.len() > 1 => {
+                for c in short[1..].chars() {
+                    match c {
+                        'l' => options.lines = true,
+                        'w' => options.words = true,
+                        'm' => options.chars = true,
+                        'c' => options.bytes = true,
+                        'L' => options.max_line_length = true,
+                        _ => return Err(UsageError::InvalidOption(c)),
+                    }
+                }
+            }
+            file => files.push(file.to_string()),
+        }
+    }
+    if options.count() == 0 {
+        options = Options {
+            parallel: options.parallel,
+            ..Options::DEFAULT
+        };
+    }
+    Ok((options, files))
+}
+
+/// Formats the selected counts, each right-aligned in `width` columns
+fn format_counts(counts: &Counts, options: &Options, width: usize) -> String {
+    let fields = [
+        (options.lines, counts.lines),
+        (options.words, counts.words),
+        (options.chars, counts.chars),
+        (options.bytes, counts.bytes),
+        (options.max_line_length, counts.max_line_length),
+    ];
+    let fields: Vec<_> = fields
+        .into_iter()
+        .filter(|&(selected, _)| selected)
+        .map(|(_, count)| format!("{count:>width$}"))
+        .collect();
+    fields.join(" ")
+}
 
-use std::env;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Lines};
+/// Returns the width of the columns, which is wide enough for the total size of the files,
+/// like GNU `wc` does. The size of standard input or a device is not known in advance,
+/// so then the width is at least 7.
+fn column_width(options: &Options, files: &[String]) -> usize {
+    if files.len() == 1 && options.count() == 1 {
+        return 1;
+    }
+    let mut min_width = 1;
+    let mut total_size = 0;
+    for file in files {
+        if file == "-" {
+            min_width = 7;
+            continue;
+        }
+        match fs::metadata(file) {
+            Ok(metadata) if metadata.is_file() => total_size += metadata.len(),
+            Ok(_) => min_width = 7,
+            // The error is reported when the file is opened
+            Err(_) => {}
+        }
+    }
+    total_size.to_string().len().max(min_width)
+}
 
-//change this into:
-//fn read_lines(filename: &str) -> Result<Lines<BufReader<File>>, io::Error> {
-fn read_lines(filename: &str) -> Lines<BufReader<File>> {
-    let file = File::open(filename).unwrap(); // this can easily fail
-    BufReader::new(file).lines()
+/// Counts standard input if `file` is `-`, and the file with that name otherwise.
+/// An error opening the file means there are no counts, but an error reading it
+/// still gives the counts up to that point.
+fn count_file(file: &str, parallel: bool) -> Result<Counts, (Option<Counts>, io::Error)> {
+    let reader: Box<dyn Read> = if file == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(file).map_err(|e| (None, e))?)
+    };
+    let result = if parallel {
+        count_parallel(reader)
+    } else {
+        count(reader)
+    };
+    result.map_err(|(counts, e)| (Some(counts), e))
 }
 
-//change this into:
-//fn count_bytes_and_lines(filename: &str) -> Result<(usize, usize, usize), io::Error> {
-fn count_bytes_and_lines(filename: &str) -> (usize, usize, usize) {
-    let lines = read_lines(filename);
-    let mut line_count = 0;
-    let mut word_count = 0;
-    let mut byte_count = 0;
-    for line in lines {
-        let text = line.unwrap(); // this will usually not fail
-        line_count += 1;
-        word_count += text.split_whitespace().count();
-        byte_count += text.len();
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (options, files) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("wc: {e}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // Without files, standard input is counted, but no name is printed
+    let (files, print_names) = if files.is_empty() {
+        (vec!["-".to_string()], false)
+    } else {
+        (files, true)
+    };
+
+    let width = column_width(&options, &files);
+    let mut total = Counts::default();
+    let mut failed = false;
+    for file in &files {
+        let counts = match count_file(file, options.parallel) {
+            Ok(counts) => Some(counts),
+            Err((counts, e)) => {
+                eprintln!("wc: {file}: {e}");
+                failed = true;
+                counts
+            }
+        };
+        if let Some(counts) = counts {
+            total.add(&counts);
+            if print_names {
+                println!("{} {file}", format_counts(&counts, &options, width));
+            } else {
+                println!("{}", format_counts(&counts, &options, width));
+            }
+        }
+    }
+    if files.len() > 1 {
+        println!("{} total", format_counts(&total, &options, width));
     }
 
-    (line_count, word_count, byte_count)
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let filename = &args[1];
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&[]), Ok((Options::DEFAULT, vec![])));
+        let lines_and_words = Options {
+            lines: true,
+            words: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            parse_args(&args(&["-l", "a", "-w", "b"])),
+            Ok((lines_and_words, args(&["a", "b"])))
+        );
+        assert_eq!(
+            parse_args(&args(&["-lw", "--", "-c", "-"])),
+            Ok((lines_and_words, args(&["-c", "-"])))
+        );
+        assert_eq!(
+            parse_args(&args(&["--lines", "--words"])),
+            Ok((lines_and_words, vec![]))
+        );
+        assert_eq!(
+            parse_args(&args(&["-mcL"])).unwrap().0,
+            Options {
+                chars: true,
+                bytes: true,
+                max_line_length: true,
+                ..Options::default()
+            }
+        );
+        // `--parallel` doesn't select a count
+        assert_eq!(
+            parse_args(&args(&["--parallel", "-"])),
+            Ok((
+                Options {
+                    parallel: true,
+                    ..Options::DEFAULT
+                },
+                args(&["-"])
+            ))
+        );
+        assert_eq!(
+            parse_args(&args(&["-lq"])),
+            Err(UsageError::InvalidOption('q'))
+        );
+        assert_eq!(
+            parse_args(&args(&["--help"])),
+            Err(UsageError::UnrecognizedOption("--help".to_string()))
+        );
+    }
 
-    let (lines, words, bytes) = count_bytes_and_lines(filename);
-    println!("{filename}: {lines} lines, {words} words, {bytes} bytes");
+    #[test]
+    fn test_format() {
+        let counts = Counts {
+            lines: 2,
+            words: 40,
+            chars: 200,
+            bytes: 210,
+            max_line_length: 80,
+        };
+        assert_eq!(format_counts(&counts, &Options::DEFAULT, 3), "  2  40 210");
+        let all = Options {
+            lines: true,
+            words: true,
+            chars: true,
+            bytes: true,
+            max_line_length: true,
+            parallel: false,
+        };
+        assert_eq!(format_counts(&counts, &all, 1), "2 40 200 210 80");
+    }
 }
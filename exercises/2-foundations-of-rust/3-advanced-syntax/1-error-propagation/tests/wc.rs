@@ -0,0 +1,196 @@
+//! Runs the `wc` binary on files and standard input, and checks its output
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+/// Creates a directory for this test with the given files in it, which the binary runs in
+fn dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents).unwrap();
+    }
+    dir
+}
+
+fn run(dir: &PathBuf, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_error-propagating"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn assert_output(output: Output, stdout: &str, stderr: &str, success: bool) {
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), stdout);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), stderr);
+    assert_eq!(output.status.success(), success);
+}
+
+const POEM: &str = "The quick brown fox\njumps over\tthe lazy dog\n";
+
+#[test]
+fn test_single_file() {
+    let dir = dir("single_file", &[("poem.txt", POEM)]);
+    assert_output(
+        run(&dir, &["poem.txt"], ""),
+        " 2  9 44 poem.txt\n",
+        "",
+        true,
+    );
+    // With a single count of a single file, there is no padding
+    assert_output(run(&dir, &["-l", "poem.txt"], ""), "2 poem.txt\n", "", true);
+    assert_output(
+        run(&dir, &["-L", "-w", "poem.txt"], ""),
+        " 9 28 poem.txt\n",
+        "",
+        true,
+    );
+    assert_output(
+        run(&dir, &["-lwmcL", "poem.txt"], ""),
+        " 2  9 44 44 28 poem.txt\n",
+        "",
+        true,
+    );
+}
+
+#[test]
+fn test_multiple_files() {
+    let long = "word ".repeat(300);
+    let dir = dir("multiple_files", &[("poem.txt", POEM), ("long.txt", &long)]);
+    // The columns are as wide as the total number of bytes
+    assert_output(
+        run(&dir, &["poem.txt", "long.txt"], ""),
+        "   2    9   44 poem.txt\n   0  300 1500 long.txt\n   2  309 1544 total\n",
+        "",
+        true,
+    );
+    // The total of `-L` is the maximum
+    assert_output(
+        run(&dir, &["-L", "poem.txt", "long.txt"], ""),
+        "  28 poem.txt\n1500 long.txt\n1500 total\n",
+        "",
+        true,
+    );
+    // A file name that starts with `-` comes after `--`
+    fs::write(dir.join("-l"), "x\n").unwrap();
+    assert_output(
+        run(&dir, &["-c", "--", "-l", "poem.txt"], ""),
+        " 2 -l\n44 poem.txt\n46 total\n",
+        "",
+        true,
+    );
+}
+
+#[test]
+fn test_stdin() {
+    let dir = dir("stdin", &[("poem.txt", POEM)]);
+    // Without files, standard input is counted and no name is printed. Its size is not known
+    // in advance, so the columns are 7 wide.
+    assert_output(run(&dir, &[], POEM), "      2       9      44\n", "", true);
+    assert_output(
+        run(&dir, &["-w", "-", "poem.txt"], POEM),
+        "      9 -\n      9 poem.txt\n     18 total\n",
+        "",
+        true,
+    );
+}
+
+#[test]
+fn test_errors() {
+    let dir = dir("errors", &[("poem.txt", POEM)]);
+    fs::create_dir(dir.join("subdir")).unwrap();
+    // The other files are still counted, and the exit status reports the failure
+    let output = run(&dir, &["missing.txt", "poem.txt"], "");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("wc: missing.txt: "));
+    assert_output(
+        Output {
+            stderr: vec![],
+            ..output
+        },
+        " 2  9 44 poem.txt\n 2  9 44 total\n",
+        "",
+        false,
+    );
+    // A directory can be opened but not read, so it gets counts like GNU `wc` gives it
+    let output = run(&dir, &["subdir", "poem.txt"], "");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("wc: subdir: "));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "      0       0       0 subdir\n      2       9      44 poem.txt\n      2       9      44 total\n"
+    );
+    assert!(!output.status.success());
+
+    assert_output(
+        run(&dir, &["-x", "poem.txt"], ""),
+        "",
+        "wc: invalid option -- 'x'\nusage: wc [-clmwL] [--parallel] [file ...]\n",
+        false,
+    );
+}
+
+/// The files in `tests/fixtures`, which include files that are not valid UTF-8
+const FIXTURES: [&str; 8] = [
+    "binary.bin",
+    "crlf.txt",
+    "empty.txt",
+    "latin1.txt",
+    "no-newline.txt",
+    "poem.txt",
+    "truncated.txt",
+    "utf8.txt",
+];
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+#[test]
+fn test_fixtures_golden() {
+    let output = run(&fixtures(), &[&["-lwmcL"], &FIXTURES[..]].concat(), "");
+    let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/fixtures.txt");
+    assert_output(output, &fs::read_to_string(golden).unwrap(), "", true);
+}
+
+#[test]
+fn test_fixtures_bytes() {
+    // The number of bytes is the size of the file, also when it's not text
+    for fixture in FIXTURES {
+        let output = run(&fixtures(), &["-c", fixture], "");
+        let size = fs::metadata(fixtures().join(fixture)).unwrap().len();
+        assert_output(output, &format!("{size} {fixture}\n"), "", true);
+    }
+}
+
+#[test]
+fn test_fixtures_parallel() {
+    let output = run(
+        &fixtures(),
+        &[&["-lwmcL", "--parallel"], &FIXTURES[..]].concat(),
+        "",
+    );
+    let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/fixtures.txt");
+    assert_output(output, &fs::read_to_string(golden).unwrap(), "", true);
+
+    assert_output(
+        run(&fixtures(), &["--parallel"], POEM),
+        "      2       9      44\n",
+        "",
+        true,
+    );
+}
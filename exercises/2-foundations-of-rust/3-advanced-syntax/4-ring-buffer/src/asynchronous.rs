@@ -0,0 +1,262 @@
+//! An asynchronous channel backed by a [`RingBuffer`]. Unlike iterating over a `RingBuffer`,
+//! which stops as soon as it's empty, the [`Receiver`] waits for the [`Sender`] to write more
+//! items, and the `Sender` waits for the `Receiver` to make room when the buffer is full.
+//!
+//! The `Receiver` is a [`Stream`] and the `Sender` is a [`Sink`], so they can be combined
+//! with the adapters in [`futures::StreamExt`] and [`futures::SinkExt`].
+
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use futures::{Sink, Stream};
+
+use crate::RingBuffer;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SendError<T> {
+    /// The `Receiver` was dropped. If that happened after `poll_ready` and before `start_send`,
+    /// this holds the item that `start_send` couldn't send.
+    ReceiverDropped(Option<T>),
+}
+
+struct Inner<T> {
+    /// The buffer containing the items
+    buffer: RingBuffer<T>,
+    /// The waker used to wake the `Receiver` when an item is written
+    rx_waker: Option<Waker>,
+    /// The waker used to wake the `Sender` when there is room for an item
+    tx_waker: Option<Waker>,
+    /// Indicates whether the `Receiver` was dropped
+    rx_dropped: bool,
+    /// Indicates whether the `Sender` was closed or dropped
+    tx_closed: bool,
+}
+
+impl<T> Inner<T> {
+    fn wake_rx(&mut self) {
+        if let Some(waker) = self.rx_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_tx(&mut self) {
+        if let Some(waker) = self.tx_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(item) = inner.buffer.read() {
+            // There is room for another item now
+            inner.wake_tx();
+            Poll::Ready(Some(item))
+        } else if inner.tx_closed {
+            Poll::Ready(None)
+        } else {
+            inner.rx_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.rx_dropped = true;
+        // A `Sender` waiting for room would otherwise wait forever
+        inner.wake_tx();
+    }
+}
+
+pub struct Sender<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+impl<T> Sink<T> for Sender<T> {
+    type Error = SendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.rx_dropped {
+            Poll::Ready(Err(SendError::ReceiverDropped(None)))
+        } else if inner.buffer.has_room() {
+            Poll::Ready(Ok(()))
+        } else {
+            inner.tx_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// # Panics
+    /// Panics if the buffer is full, which can't happen if `poll_ready` returned `Ready(Ok(()))`
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.rx_dropped {
+            return Err(SendError::ReceiverDropped(Some(item)));
+        }
+        if inner.buffer.write(item).is_err() {
+            panic!("`poll_ready` must be called before `start_send`");
+        }
+        inner.wake_rx();
+        Ok(())
+    }
+
+    /// Items are available to the `Receiver` as soon as they are sent, so there is nothing to flush
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// After closing, the `Receiver` yields the remaining items, and then ends
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tx_closed = true;
+        inner.wake_rx();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tx_closed = true;
+        inner.wake_rx();
+    }
+}
+
+/// Create a new channel backed by a `RingBuffer` with `size` slots, which can hold `size - 1` items.
+///
+/// # Panics
+/// Panics if `size` is less than 2, because then no item could ever be sent
+pub fn channel<T>(size: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(
+        size >= 2,
+        "a channel needs at least 2 slots, but got {size}"
+    );
+    let inner = Inner {
+        buffer: RingBuffer::new(size),
+        rx_waker: None,
+        tx_waker: None,
+        rx_dropped: false,
+        tx_closed: false,
+    };
+    let inner = Arc::new(Mutex::new(inner));
+    let tx = Sender {
+        inner: inner.clone(),
+    };
+    let rx = Receiver { inner };
+    (tx, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use futures::{stream, task::noop_waker_ref, Sink, SinkExt, StreamExt};
+    use tokio::{task, time};
+
+    use crate::asynchronous::{channel, SendError};
+
+    #[tokio::test]
+    async fn test_send_recv() {
+        let (mut tx, mut rx) = channel(4);
+        for i in 0..100 {
+            tx.send(i).await.unwrap();
+            assert_eq!(rx.next().await.unwrap(), i);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_room() {
+        let (mut tx, mut rx) = channel(3);
+        tx.send(1).await.unwrap();
+        tx.send(2).await.unwrap();
+        // The buffer is full, so this send only completes after the receiver reads an item
+        let sender = task::spawn(async move {
+            tx.send(3).await.unwrap();
+            tx
+        });
+        time::sleep(Duration::from_millis(10)).await;
+        assert!(!sender.is_finished());
+        assert_eq!(rx.next().await, Some(1));
+        let tx = sender.await.unwrap();
+        drop(tx);
+        assert_eq!(rx.collect::<Vec<_>>().await, [2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_items() {
+        let (mut tx, mut rx) = channel(4);
+        let receiver = task::spawn(async move { rx.next().await });
+        time::sleep(Duration::from_millis(10)).await;
+        assert!(!receiver.is_finished());
+        tx.send("hello").await.unwrap();
+        assert_eq!(receiver.await.unwrap(), Some("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_drop() {
+        let (mut tx, mut rx) = channel(4);
+        tx.send(1).await.unwrap();
+        tx.close().await.unwrap();
+        // The remaining items are still received
+        assert_eq!(rx.next().await, Some(1));
+        assert_eq!(rx.next().await, None);
+
+        let (tx, mut rx) = channel::<()>(4);
+        drop(tx);
+        assert_eq!(rx.next().await, None);
+
+        let (mut tx, rx) = channel(4);
+        drop(rx);
+        assert_eq!(tx.send(()).await, Err(SendError::ReceiverDropped(None)));
+
+        // A sender waiting for room is woken up when the receiver is dropped
+        let (mut tx, rx) = channel(2);
+        tx.send(1).await.unwrap();
+        let sender = task::spawn(async move { tx.send(2).await });
+        time::sleep(Duration::from_millis(10)).await;
+        drop(rx);
+        assert_eq!(sender.await.unwrap(), Err(SendError::ReceiverDropped(None)));
+
+        // When the receiver is dropped after there was room, the item is returned
+        let (mut tx, rx) = channel(2);
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert_eq!(Pin::new(&mut tx).poll_ready(&mut cx), Poll::Ready(Ok(())));
+        drop(rx);
+        assert_eq!(
+            Pin::new(&mut tx).start_send("item"),
+            Err(SendError::ReceiverDropped(Some("item")))
+        );
+    }
+
+    #[test]
+    #[should_panic = "a channel needs at least 2 slots, but got 1"]
+    fn test_too_small() {
+        let _ = channel::<()>(1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_forward_through_small_buffer() {
+        let (tx, rx) = channel(4);
+        let sender = task::spawn(stream::iter(0..10_000).map(Ok).forward(tx));
+        let received: Vec<_> = rx.collect().await;
+        sender.await.unwrap().unwrap();
+        assert_eq!(received, (0..10_000).collect::<Vec<_>>());
+    }
+}
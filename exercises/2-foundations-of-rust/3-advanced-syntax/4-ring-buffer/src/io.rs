@@ -0,0 +1,184 @@
+//! A `RingBuffer<u8>` is a byte pipe: bytes written to it can be read back in the same order.
+//! These implementations of [`io::Read`], [`io::BufRead`] and [`io::Write`] copy whole
+//! slices at a time, rather than moving a single byte at a time like `read` and `write` do.
+//!
+//! Just like writing to a `&mut [u8]`, writing to a full `RingBuffer` that isn't overwriting
+//! writes 0 bytes. Reading from an empty `RingBuffer` reads 0 bytes, which
+//! signals the end of the data to for example [`io::copy`].
+//!
+//! Note that the inherent `RingBuffer::read` and `RingBuffer::write` take precedence over
+//! `io::Read::read` and `io::Write::write` in method calls. Call the latter as
+//! `io::Write::write(&mut queue, bytes)`, or use methods like `write_all` and `read_exact`.
+
+use std::io;
+use std::mem::MaybeUninit;
+use std::ptr;
+
+use crate::RingBuffer;
+
+impl RingBuffer<u8> {
+    /// Returns the free slots, in the order they are written to. The slot that
+    /// is always left free is not included.
+    fn free_slots_mut(&mut self) -> (&mut [MaybeUninit<u8>], &mut [MaybeUninit<u8>]) {
+        let free = self.capacity() - self.len();
+        let first = free.min(self.data.len() - self.end);
+        let (before, after) = self.data.split_at_mut(self.end);
+        (&mut after[..first], &mut before[..free - first])
+    }
+
+    /// Moves `start` forward by `n` slots, removing the `n` oldest bytes.
+    /// As `u8` is `Copy`, they don't need to be dropped.
+    fn advance_start(&mut self, n: usize) {
+        debug_assert!(n <= self.len());
+        if n > 0 {
+            self.start = (self.start + n) % self.data.len();
+        }
+    }
+
+    /// Moves `end` forward by `n` slots, which must have been initialized
+    fn advance_end(&mut self, n: usize) {
+        debug_assert!(n <= self.capacity() - self.len());
+        if n > 0 {
+            self.end = (self.end + n) % self.data.len();
+        }
+    }
+}
+
+impl io::Read for RingBuffer<u8> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (front, back) = self.as_slices();
+        let n_front = front.len().min(buf.len());
+        buf[..n_front].copy_from_slice(&front[..n_front]);
+        let n_back = back.len().min(buf.len() - n_front);
+        buf[n_front..n_front + n_back].copy_from_slice(&back[..n_back]);
+
+        self.advance_start(n_front + n_back);
+        Ok(n_front + n_back)
+    }
+}
+
+impl io::BufRead for RingBuffer<u8> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(self.as_slices().0)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.advance_start(amt.min(self.len()));
+    }
+}
+
+/// Copies `src` into `dst`, which has the same length, like `copy_from_slice` does on the `Read` side.
+/// This is `<[MaybeUninit<u8>]>::write_copy_of_slice`, which is too recent for the `rust-version` of this crate.
+fn copy_to_uninit(dst: &mut [MaybeUninit<u8>], src: &[u8]) {
+    assert_eq!(dst.len(), src.len(), "the slices must have the same length");
+    // SAFETY: `dst` has room for `src.len()` bytes, as the lengths are equal, and `MaybeUninit<u8>`
+    // has the same layout as `u8`. A `&mut` slice can't overlap with a `&` slice.
+    unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dst.as_mut_ptr().cast::<u8>(), src.len()) }
+}
+
+impl io::Write for RingBuffer<u8> {
+    /// Writes as many bytes from `buf` as there is room for. If the buffer is overwriting,
+    /// all of `buf` is written, evicting the oldest bytes as needed. Of a `buf` longer than
+    /// the capacity, only the last bytes are kept.
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        let written = buf.len();
+        if self.overwrite {
+            buf = &buf[buf.len().saturating_sub(self.capacity())..];
+            let evicted = buf.len().saturating_sub(self.capacity() - self.len());
+            self.advance_start(evicted);
+        }
+
+        let (front, back) = self.free_slots_mut();
+        let n_front = front.len().min(buf.len());
+        copy_to_uninit(&mut front[..n_front], &buf[..n_front]);
+        let n_back = back.len().min(buf.len() - n_front);
+        copy_to_uninit(&mut back[..n_back], &buf[n_front..n_front + n_back]);
+
+        self.advance_end(n_front + n_back);
+        if self.overwrite {
+            Ok(written)
+        } else {
+            Ok(n_front + n_back)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RingBuffer;
+    use std::io::{self, BufRead, Read, Write};
+
+    #[test]
+    fn test_bulk_write_and_read() {
+        let mut queue = RingBuffer::new(8);
+        assert_eq!(Write::write(&mut queue, b"hello").unwrap(), 5);
+        let mut buf = [0; 3];
+        assert_eq!(Read::read(&mut queue, &mut buf).unwrap(), 3);
+        assert_eq!(&buf, b"hel");
+
+        // This write wraps around the end of the buffer, and only 5 bytes fit
+        assert_eq!(Write::write(&mut queue, b", world").unwrap(), 5);
+        assert_eq!(queue.as_slices(), (&b"lo, w"[..], &b"or"[..]));
+        assert_eq!(Write::write(&mut queue, b"!").unwrap(), 0);
+        assert!(queue.write_all(b"!").is_err());
+
+        let mut buf = [0; 16];
+        assert_eq!(Read::read(&mut queue, &mut buf).unwrap(), 7);
+        assert_eq!(&buf[..7], b"lo, wor");
+        assert_eq!(Read::read(&mut queue, &mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_no_slots() {
+        let mut queue = RingBuffer::new(0);
+        assert_eq!(Write::write(&mut queue, b"hello").unwrap(), 0);
+        assert_eq!(Read::read(&mut queue, &mut [0; 4]).unwrap(), 0);
+
+        let mut queue = RingBuffer::new_overwriting(0);
+        assert_eq!(Write::write(&mut queue, b"hello").unwrap(), 5);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_overwriting_keeps_the_newest_bytes() {
+        let mut queue = RingBuffer::new_overwriting(5);
+        assert_eq!(Write::write(&mut queue, b"abc").unwrap(), 3);
+        assert_eq!(Write::write(&mut queue, b"de").unwrap(), 2);
+        assert_eq!(Write::write(&mut queue, b"fg").unwrap(), 2);
+        assert_eq!(queue.iter().copied().collect::<Vec<_>>(), b"defg");
+        assert_eq!(Write::write(&mut queue, b"0123456789").unwrap(), 10);
+        assert_eq!(queue.iter().copied().collect::<Vec<_>>(), b"6789");
+    }
+
+    #[test]
+    fn test_buf_read_lines() {
+        let mut queue = RingBuffer::new(16);
+        queue.write_all(b"0123456789").unwrap();
+        io::copy(&mut Read::take(&mut queue, 10), &mut io::sink()).unwrap();
+        // The lines wrap around the end of the buffer
+        queue.write_all(b"one\ntwo\nthree").unwrap();
+        let lines: Vec<String> = queue.lines().map(Result::unwrap).collect();
+        assert_eq!(lines, ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn test_stream_through() {
+        let input: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let mut decoder = &input[..];
+        let mut socket = Vec::new();
+        let mut queue = RingBuffer::new(64);
+        loop {
+            let room = (queue.capacity() - queue.len()) as u64;
+            let written = io::copy(&mut (&mut decoder).take(room), &mut queue).unwrap();
+            let read = io::copy(&mut queue, &mut socket).unwrap();
+            if written == 0 && read == 0 {
+                break;
+            }
+        }
+        assert_eq!(socket, input);
+    }
+}
@@ -0,0 +1,355 @@
+//! One way to implement a queue is to use a linked list; however, that requires a lot of dynamic memory manipulation to add/remove individual items.
+//! A more low-level approach is to use a circular buffer: the compromise is that the capacity of the queue is then "fixed". For a background on circular buffers,
+//! you can consult https://en.wikipedia.org/wiki/Circular_buffer
+//!
+//! This crate provides a [`RingBuffer`] that can hold items of any type. Its size is chosen at runtime,
+//! and its slots are a `Box<[MaybeUninit<T>]>`, so `T` doesn't need to implement `Default`
+//! to fill up the slots that are not in use. A `RingBuffer<u8>` implements `std::io::Read`,
+//! `std::io::BufRead` and `std::io::Write`, so that it can be used as a byte pipe.
+//! [`RingBuffer::split`] turns a `RingBuffer` into a [`Producer`] and a [`Consumer`]
+//! that can be used from two different threads. The [`asynchronous`] module provides a
+//! channel backed by a `RingBuffer`, of which the halves wait for items or room asynchronously.
+
+use std::mem::MaybeUninit;
+use std::{iter, slice};
+
+pub mod asynchronous;
+mod io;
+mod spsc;
+
+pub use spsc::{Consumer, Producer};
+
+/// A first-in, first-out queue with a fixed number of slots.
+///
+/// # How many items fit?
+/// A `RingBuffer` with `size` slots can hold at most `size - 1` items at a time. The buffer keeps
+/// track of where the oldest item is (`start`) and where the next item goes (`end`).
+/// If all slots could be filled, `start == end` would mean both "empty" and "full",
+/// so one slot is always left free to tell those apart. As a consequence:
+///
+/// - `RingBuffer::new(0)` has no slots at all, and can't hold any item.
+/// - `RingBuffer::new(1)` only has the free slot, and can't hold any item either.
+/// - `RingBuffer::new(n)` can hold `n - 1` items for any `n >= 1`. See [`RingBuffer::capacity`].
+///
+/// # Overwriting
+/// By default, writing to a full `RingBuffer` fails. A `RingBuffer` created with
+/// [`RingBuffer::new_overwriting`] makes room for the new item instead, by evicting the oldest one.
+/// That is useful to keep the tail of a log, where the newest data is the most interesting.
+pub struct RingBuffer<T> {
+    data: Box<[MaybeUninit<T>]>,
+    /// The slot of the oldest item, if any
+    start: usize,
+    /// The slot the next item is written to
+    end: usize,
+    /// Whether writing to a full buffer evicts the oldest item
+    overwrite: bool,
+}
+
+impl<T> RingBuffer<T> {
+    /// Creates a `RingBuffer` with `size` slots, which can hold `size - 1` items
+    pub fn new(size: usize) -> RingBuffer<T> {
+        RingBuffer {
+            data: Box::new_uninit_slice(size),
+            start: 0,
+            end: 0,
+            overwrite: false,
+        }
+    }
+
+    /// Creates a `RingBuffer` with `size` slots, which can hold `size - 1` items,
+    /// and which evicts the oldest item when writing to it while it's full
+    pub fn new_overwriting(size: usize) -> RingBuffer<T> {
+        let mut queue = RingBuffer::new(size);
+        queue.overwrite = true;
+        queue
+    }
+
+    /// Returns true if writing to the queue while it's full evicts the oldest item
+    pub fn is_overwriting(&self) -> bool {
+        self.overwrite
+    }
+
+    /// The number of items in the queue
+    pub fn len(&self) -> usize {
+        if self.end >= self.start {
+            self.end - self.start
+        } else {
+            self.end + self.data.len() - self.start
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The number of items the queue can hold at one time, which is one less than its number of slots
+    pub fn capacity(&self) -> usize {
+        self.data.len().saturating_sub(1)
+    }
+
+    /// Returns true if the queue holds as many items as it can. Writing to a full queue
+    /// fails, unless it's overwriting
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    /// Returns true if and only if writing to the queue will succeed without evicting an item
+    pub fn has_room(&self) -> bool {
+        !self.is_full()
+    }
+
+    /// This function tries to read a value from the queue and returns Some(value) if this succeeds,
+    /// it returns None if the queue was empty
+    pub fn read(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        // SAFETY: the slots from `start` up to `end` are initialized, and
+        // moving `start` past this slot marks it as uninitialized again
+        let value = unsafe { self.data[self.start].assume_init_read() };
+        self.start = self.slot_after(self.start);
+        Some(value)
+    }
+
+    /// Returns the same thing as [`RingBuffer::read`], but leaves the element in the queue
+    pub fn peek(&self) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+        // SAFETY: the slots from `start` up to `end` are initialized
+        Some(unsafe { self.data[self.start].assume_init_ref() })
+    }
+
+    /// This function tries to put `value` on the queue; and returns Ok if this succeeds
+    /// It returns an error if writing to the queue failed (which can happen if there is not enough room),
+    /// in which case `value` is handed back as `Err(value)`, so that it isn't lost
+    ///
+    /// If the queue is overwriting, writing always succeeds. If the queue was full,
+    /// the oldest item is evicted and returned as `Ok(Some(oldest))`. A queue that can't
+    /// hold any item at all hands back `value` itself that way.
+    pub fn write(&mut self, value: T) -> Result<Option<T>, T> {
+        let evicted = if self.is_full() {
+            if !self.overwrite {
+                return Err(value);
+            }
+            if self.capacity() == 0 {
+                return Ok(Some(value));
+            }
+            self.read()
+        } else {
+            None
+        };
+        self.data[self.end].write(value);
+        self.end = self.slot_after(self.end);
+        Ok(evicted)
+    }
+
+    /// Returns an iterator over the items in the queue, from oldest to newest,
+    /// without removing them
+    pub fn iter(&self) -> iter::Chain<slice::Iter<'_, T>, slice::Iter<'_, T>> {
+        let (front, back) = self.as_slices();
+        front.iter().chain(back)
+    }
+
+    /// Returns the items in the queue as two slices. The items in the first slice are
+    /// older than those in the second one, which is only non-empty if the items wrap
+    /// around the end of the buffer.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (front, back) = if self.end >= self.start {
+            (&self.data[self.start..self.end], &self.data[..0])
+        } else {
+            (&self.data[self.start..], &self.data[..self.end])
+        };
+        // SAFETY: the slots from `start` up to `end` are initialized,
+        // and `MaybeUninit<T>` has the same layout as `T`
+        unsafe {
+            (
+                &*(front as *const [MaybeUninit<T>] as *const [T]),
+                &*(back as *const [MaybeUninit<T>] as *const [T]),
+            )
+        }
+    }
+
+    /// The slot after `index`. Only called if there is at least one slot, so that
+    /// the modulo never divides by zero
+    fn slot_after(&self, index: usize) -> usize {
+        (index + 1) % self.data.len()
+    }
+}
+
+impl<T> Drop for RingBuffer<T> {
+    fn drop(&mut self) {
+        while self.read().is_some() {}
+    }
+}
+
+/// This is a fun extra bit: by defining an "iterator", a ring buffer we defined ourselves can be used in for loops! (We will explain this feature in a later module!)
+impl<T> Iterator for RingBuffer<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.read()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_empty() {
+        let mut queue = RingBuffer::new(0);
+        assert_eq!(queue.write(1), Err(1));
+    }
+
+    #[test]
+    fn test_single() {
+        let mut queue = RingBuffer::new(1);
+        assert_eq!(queue.write(1), Err(1));
+    }
+
+    #[test]
+    fn test_enough_size() {
+        let mut queue = RingBuffer::new(3);
+        assert!(queue.write(1).is_ok());
+        assert!(queue.has_room());
+        assert!(queue.write(2).is_ok());
+        assert!(queue.read() == Some(1));
+        assert!(queue.write(3).is_ok());
+        assert!(queue.peek() == Some(&2));
+        assert!(queue.read() == Some(2));
+        assert!(queue.write(4).is_ok());
+    }
+
+    #[test]
+    fn test_not_enough_size() {
+        let mut queue = RingBuffer::new(3);
+        assert!(queue.write(1).is_ok());
+        assert!(queue.read() == Some(1));
+        assert!(queue.write(2).is_ok());
+        assert!(queue.read() == Some(2));
+        assert!(queue.write(3).is_ok());
+        assert!(queue.write(4).is_ok());
+        assert_eq!(queue.write(5), Err(5));
+    }
+
+    #[test]
+    fn test_no_slots() {
+        let mut queue: RingBuffer<u8> = RingBuffer::new(0);
+        assert_eq!(queue.capacity(), 0);
+        assert_eq!(queue.len(), 0);
+        assert!(queue.is_full());
+        assert!(!queue.has_room());
+        assert_eq!(queue.peek(), None);
+        assert_eq!(queue.read(), None);
+    }
+
+    #[test]
+    fn test_holds_one_less_than_its_size() {
+        for size in 1..8 {
+            let mut queue = RingBuffer::new(size);
+            assert_eq!(queue.capacity(), size - 1);
+            let mut written = 0;
+            while queue.write(written).is_ok() {
+                written += 1;
+            }
+            assert_eq!(written, size - 1);
+            assert_eq!(queue.len(), size - 1);
+            assert!(queue.is_full());
+        }
+    }
+
+    #[test]
+    fn test_wraps_around() {
+        let mut queue = RingBuffer::new(4);
+        for i in 0..100 {
+            assert!(queue.write(i).is_ok());
+            assert!(queue.write(i + 1000).is_ok());
+            assert_eq!(queue.len(), 2);
+            assert_eq!(queue.read(), Some(i));
+            assert_eq!(queue.read(), Some(i + 1000));
+            assert!(queue.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_items_without_default() {
+        let mut queue = RingBuffer::new(3);
+        assert!(queue.write(String::from("hello")).is_ok());
+        assert!(queue.write(String::from("world")).is_ok());
+        assert_eq!(queue.peek().map(String::as_str), Some("hello"));
+        // A full queue hands back what it can't hold
+        assert_eq!(queue.write(String::from("!")), Err(String::from("!")));
+        assert_eq!(queue.collect::<Vec<_>>(), ["hello", "world"]);
+    }
+
+    #[test]
+    fn test_drops_remaining_items() {
+        let item = Rc::new(());
+        let mut queue = RingBuffer::new(4);
+        for _ in 0..3 {
+            assert!(queue.write(Rc::clone(&item)).is_ok());
+        }
+        // Rejected items are handed back, and are dropped along with the result
+        let rejected = queue.write(Rc::clone(&item));
+        assert!(matches!(&rejected, Err(rejected) if Rc::ptr_eq(rejected, &item)));
+        assert_eq!(Rc::strong_count(&item), 5);
+        drop(rejected);
+        assert_eq!(Rc::strong_count(&item), 4);
+        drop(queue.read());
+        assert_eq!(Rc::strong_count(&item), 3);
+        drop(queue);
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn test_overwrites_oldest() {
+        let mut queue = RingBuffer::new_overwriting(4);
+        assert!(queue.is_overwriting());
+        assert_eq!(queue.write(1), Ok(None));
+        assert_eq!(queue.write(2), Ok(None));
+        assert_eq!(queue.write(3), Ok(None));
+        assert!(queue.is_full());
+        assert!(!queue.has_room());
+        assert_eq!(queue.write(4), Ok(Some(1)));
+        assert_eq!(queue.write(5), Ok(Some(2)));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.read(), Some(3));
+        assert_eq!(queue.write(6), Ok(None));
+        assert_eq!(queue.write(7), Ok(Some(4)));
+        assert_eq!(queue.collect::<Vec<_>>(), [5, 6, 7]);
+    }
+
+    #[test]
+    fn test_overwriting_without_room() {
+        for size in [0, 1] {
+            let mut queue = RingBuffer::new_overwriting(size);
+            assert_eq!(queue.write(1), Ok(Some(1)));
+            assert!(queue.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_iter_and_slices() {
+        let mut queue = RingBuffer::new(4);
+        assert_eq!(queue.as_slices(), (&[][..], &[][..]));
+        for i in 0..3 {
+            assert!(queue.write(i).is_ok());
+        }
+        assert_eq!(queue.as_slices(), (&[0, 1, 2][..], &[][..]));
+
+        // Wrap around the end of the buffer
+        assert_eq!(queue.read(), Some(0));
+        assert_eq!(queue.read(), Some(1));
+        assert!(queue.write(3).is_ok());
+        assert!(queue.write(4).is_ok());
+        assert_eq!(queue.as_slices(), (&[2, 3][..], &[4][..]));
+        assert_eq!(queue.iter().copied().collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(queue.iter().rev().copied().collect::<Vec<_>>(), [4, 3, 2]);
+
+        // Iterating doesn't remove the items
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.peek(), Some(&2));
+    }
+}
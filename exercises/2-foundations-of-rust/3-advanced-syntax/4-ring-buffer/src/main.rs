@@ -1,141 +1,14 @@
-//! One way to implement a queue is to use a linked list; however, that requires a lot of dynamic memory manipulation to add/remove individual items.
-//! A more low-level approach is to use a circular buffer: the compromise is that the capacity of the queue is then "fixed". For a background on circular buffers,
-//! you can consult https://en.wikipedia.org/wiki/Circular_buffer
+use ring_buffer::RingBuffer;
 
-// A partial implementation is provided below; please finish it and add some more methods; please remember to run 'cargo fmt' and 'cargo clippy' after
-// every step to get feedback from the rust compiler!
-
-// 1) implement read()
-
-// 2) the queue now has a fixed size; change the definition so that the data member becomes a Box<[u8]>; you can use the provided function 'make_box' to make
-// boxed slices of arbitrary sizes. Make changes to your method definitions as needed (the definition of 'write' should not need changes!)
-
-// 3) change the method 'new()' into 'new(size: usize)' that initializes a ring buffer of the given size (instead of a fixed size of 16); use the 'make_box' function.
-
-// 4) in a queue that has size N, how many elements can be stored at one time? (test your answer experimentally)
-
-// 5) EXTRA EXERCISES:
-//  - implement the method "has_room" so that "queue.has_room()" is true if and only if writing to the queue will succeed
-//  - implement the method "peek" so that "queue.peek()" returns the same thing as "queue.read()", but leaves the element in the queue
-//  - eliminate edge cases of the data structure (HINT: what is the behaviour of (n % 0))
-//  - test your implementation by running 'cargo test'
-
-const RING_SIZE: usize = 16;
-
-struct RingBuffer {
-    data: [u8; RING_SIZE],
-    start: usize,
-    end: usize,
-}
-
-impl RingBuffer {
-    fn new() -> RingBuffer {
-        RingBuffer {
-            data: [0; RING_SIZE],
-            start: 0,
-            end: 0,
-        }
-    }
-
-    /// This function tries to read a value from the queue and returns Some(value) if this succeeds,
-    /// it returns None if the queue was empty
-
-    fn read(&mut self) -> Option<u8> {
-        todo!()
-    }
-
-    /// This function tries to put `value` on the queue; and returns true if this succeeds
-    /// It returns false if writing to the queue failed (which can happen if there is not enough room)
-
-    fn write(&mut self, value: u8) -> Result<(), &'static str> {
-        self.data[self.end] = value;
-        let pos = (self.end + 1) % self.data.len();
-        if pos == self.start {
-            Err("the buffer can hold no more new data")
-        } else {
-            self.end = pos;
-
-            Ok(())
+fn main() {
+    let mut queue = RingBuffer::new(4);
+    for word in ["one", "two", "three", "four", "five"] {
+        // A full queue hands back the item it has no room for
+        if let Err(rejected) = queue.write(word.to_string()) {
+            println!("no room for {rejected}");
         }
     }
-
-    fn has_room(&mut self) -> bool {
-        todo!()
-    }
-
-    fn peek(&mut self) -> Option<u8> {
-        todo!()
-    }
-}
-
-/// This function creates an "owned slice" a user-selectable size by allocating it as a vector (filled with zeros) using vec![], and then turning it
-/// into a Box<[u8]> using the into_boxed_slice() method, see https://doc.rust-lang.org/std/vec/struct.Vec.html#method.into_boxed_slice
-
-fn make_box(reqsize: usize) -> Box<[u8]> {
-    vec![0; reqsize].into_boxed_slice()
-}
-
-/// This is a fun extra bit: by defining an "iterator", a ring buffer we defined ourselves can be used in for loops! (We will explain this feature in a later module!)
-
-impl Iterator for RingBuffer {
-    type Item = u8;
-
-    fn next(&mut self) -> Option<u8> {
-        self.read()
-    }
-}
-
-fn main() {
-    let mut queue = RingBuffer::new();
-    assert!(queue.write(1).is_ok());
-    assert!(queue.write(2).is_ok());
-    assert!(queue.write(3).is_ok());
-    assert!(queue.write(4).is_ok());
-    assert!(queue.write(5).is_ok());
     for elem in queue {
         println!("{elem}");
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_empty() {
-        // To make this test pass you will need to edit the write function.
-        let mut queue = RingBuffer::new(0);
-        assert!(queue.write(1).is_err());
-    }
-
-    #[test]
-    fn test_single() {
-        let mut queue = RingBuffer::new(1);
-        assert!(queue.write(1).is_err());
-    }
-
-    #[test]
-    fn test_enough_size() {
-        let mut queue = RingBuffer::new(3);
-        assert!(queue.write(1).is_ok());
-        assert!(queue.has_room());
-        assert!(queue.write(2).is_ok());
-        assert!(queue.read() == Some(1));
-        assert!(queue.write(3).is_ok());
-        assert!(queue.peek() == Some(2));
-        assert!(queue.read() == Some(2));
-        assert!(queue.write(4).is_ok());
-    }
-
-    #[test]
-    fn test_not_enough_size() {
-        let mut queue = RingBuffer::new(3);
-        assert!(queue.write(1).is_ok());
-        assert!(queue.read() == Some(1));
-        assert!(queue.write(2).is_ok());
-        assert!(queue.read() == Some(2));
-        assert!(queue.write(3).is_ok());
-        assert!(queue.write(4).is_ok());
-        assert!(queue.write(5).is_err());
-    }
-}
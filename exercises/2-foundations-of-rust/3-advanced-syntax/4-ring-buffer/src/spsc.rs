@@ -0,0 +1,355 @@
+//! A `RingBuffer` can be split into a [`Producer`] and a [`Consumer`], which can be moved to
+//! different threads: one thread writes items, and another thread reads them.
+//!
+//! Unlike the `Mutex` exercise, no thread ever waits for another one to release a lock.
+//! The producer is the only one that moves `end` forward, and the consumer is the only one
+//! that moves `start` forward. Each side publishes its index with a `Release` store, and reads
+//! the index of the other side with an `Acquire` load. That way, the consumer only reads
+//! a slot after the producer has written it, and the producer only overwrites a slot after
+//! the consumer has moved the item out of it.
+//!
+//! The atomics and `UnsafeCell` come from `loom` when compiling with `--cfg loom`,
+//! so that the tests in `loom_tests` can check all possible interleavings:
+//!
+//! ```sh
+//! RUSTFLAGS="--cfg loom" cargo test --release loom
+//! ```
+
+use std::mem::{self, MaybeUninit};
+
+use crate::RingBuffer;
+use sync::{Arc, AtomicUsize, Ordering, UnsafeCell};
+
+impl<T> RingBuffer<T> {
+    /// Splits the `RingBuffer` into a [`Producer`] that writes items, and a [`Consumer`] that
+    /// reads them. Items that are already in the buffer are kept. The halves never overwrite
+    /// items, even if the `RingBuffer` was overwriting, as only the consumer can remove items.
+    pub fn split(mut self) -> (Producer<T>, Consumer<T>) {
+        let data = mem::take(&mut self.data);
+        let shared = Arc::new(Shared {
+            slots: data.into_vec().into_iter().map(UnsafeCell::new).collect(),
+            start: AtomicUsize::new(self.start),
+            end: AtomicUsize::new(self.end),
+        });
+        // The items now belong to `shared`, so they must not be dropped along with `self`
+        self.start = 0;
+        self.end = 0;
+        (
+            Producer {
+                shared: Arc::clone(&shared),
+            },
+            Consumer { shared },
+        )
+    }
+}
+
+struct Shared<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// The slot of the oldest item. Only moved forward by the consumer
+    start: AtomicUsize,
+    /// The slot the next item is written to. Only moved forward by the producer
+    end: AtomicUsize,
+}
+
+impl<T> Shared<T> {
+    /// The slot after `index`. Only called if there is at least one slot
+    fn slot_after(&self, index: usize) -> usize {
+        (index + 1) % self.slots.len()
+    }
+
+    /// The number of items in the buffer. As the other side may be reading or writing
+    /// at the same time, this may already be outdated when it's returned.
+    fn len(&self) -> usize {
+        let start = self.start.load(Ordering::Acquire);
+        let end = self.end.load(Ordering::Acquire);
+        if end >= start {
+            end - start
+        } else {
+            end + self.slots.len() - start
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots.len().saturating_sub(1)
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        // Both halves are gone, so nobody else is accessing the slots anymore
+        let mut start = self.start.load(Ordering::Acquire);
+        let end = self.end.load(Ordering::Acquire);
+        while start != end {
+            // SAFETY: the slots from `start` up to `end` are initialized
+            self.slots[start].with_mut(|slot| unsafe { (*slot).assume_init_drop() });
+            start = self.slot_after(start);
+        }
+    }
+}
+
+/// The half of a split `RingBuffer` that writes items
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// The half of a split `RingBuffer` that reads items
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+// `Shared<T>` contains `UnsafeCell`s, so it isn't `Sync`, and `Arc<Shared<T>>` isn't `Send`.
+// Yet moving a `Producer` or `Consumer` to another thread is the whole point of splitting.
+//
+// It's fine to do so, because the producer and the consumer never access the same slot at the
+// same time: see the module documentation. What does cross threads are the items themselves:
+// the producer moves an item into the buffer on one thread, and the consumer moves it out on
+// another thread. That's only allowed if `T: Send`.
+//
+// Note that `T: Sync` isn't needed: neither half hands out references to items,
+// so two threads never look at the same item at the same time.
+unsafe impl<T: Send> Send for Producer<T> {}
+unsafe impl<T: Send> Send for Consumer<T> {}
+
+impl<T> Producer<T> {
+    /// Puts `value` on the queue, or hands it back in an `Err` if the queue is full
+    pub fn write(&mut self, value: T) -> Result<(), T> {
+        let shared = &*self.shared;
+        if shared.slots.is_empty() {
+            return Err(value);
+        }
+        // Only we move `end`, so we don't need to synchronize with anyone to read it
+        let end = shared.end.load(Ordering::Relaxed);
+        // Synchronizes with the `Release` store in `Consumer::read`, so that
+        // the consumer is done with the slots before `start`
+        let start = shared.start.load(Ordering::Acquire);
+        let next = shared.slot_after(end);
+        if next == start {
+            return Err(value);
+        }
+        // SAFETY: the slot at `end` is free, and the consumer doesn't
+        // access it until we move `end` past it
+        shared.slots[end].with_mut(|slot| unsafe { (*slot).write(value) });
+        shared.end.store(next, Ordering::Release);
+        Ok(())
+    }
+
+    /// The number of items in the queue. As the consumer may be reading at the same time,
+    /// this is an upper bound
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of items the queue can hold at one time
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+}
+
+impl<T> Consumer<T> {
+    /// Takes the oldest value from the queue, or returns `None` if the queue is empty
+    pub fn read(&mut self) -> Option<T> {
+        let shared = &*self.shared;
+        // Only we move `start`, so we don't need to synchronize with anyone to read it
+        let start = shared.start.load(Ordering::Relaxed);
+        // Synchronizes with the `Release` store in `Producer::write`, so that
+        // the items before `end` are completely written
+        let end = shared.end.load(Ordering::Acquire);
+        if start == end {
+            return None;
+        }
+        // SAFETY: the slot at `start` is initialized, and the producer doesn't
+        // access it until we move `start` past it
+        let value = shared.slots[start].with(|slot| unsafe { (*slot).assume_init_read() });
+        shared
+            .start
+            .store(shared.slot_after(start), Ordering::Release);
+        Some(value)
+    }
+
+    /// The number of items in the queue. As the producer may be writing at the same time,
+    /// this is a lower bound
+    pub fn len(&self) -> usize {
+        self.shared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of items the queue can hold at one time
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+}
+
+#[cfg(loom)]
+mod sync {
+    pub(crate) use loom::cell::UnsafeCell;
+    pub(crate) use loom::sync::atomic::{AtomicUsize, Ordering};
+    pub(crate) use loom::sync::Arc;
+}
+
+#[cfg(not(loom))]
+mod sync {
+    pub(crate) use std::sync::atomic::{AtomicUsize, Ordering};
+    pub(crate) use std::sync::Arc;
+
+    /// A `std::cell::UnsafeCell` with the API of `loom::cell::UnsafeCell`
+    pub(crate) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+    impl<T> UnsafeCell<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(std::cell::UnsafeCell::new(value))
+        }
+
+        pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+            f(self.0.get())
+        }
+
+        pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+            f(self.0.get())
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::RingBuffer;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_split_keeps_items() {
+        let mut queue = RingBuffer::new(4);
+        assert!(queue.write(1).is_ok());
+        assert!(queue.write(2).is_ok());
+        let (mut producer, mut consumer) = queue.split();
+        assert_eq!(producer.capacity(), 3);
+        assert_eq!(consumer.len(), 2);
+        assert_eq!(producer.write(3), Ok(()));
+        assert_eq!(producer.write(4), Err(4));
+        assert_eq!(consumer.read(), Some(1));
+        assert_eq!(producer.write(4), Ok(()));
+        assert_eq!(consumer.read(), Some(2));
+        assert_eq!(consumer.read(), Some(3));
+        assert_eq!(consumer.read(), Some(4));
+        assert_eq!(consumer.read(), None);
+        assert!(producer.is_empty());
+    }
+
+    #[test]
+    fn test_no_slots() {
+        let (mut producer, mut consumer) = RingBuffer::new(0).split();
+        assert_eq!(producer.write(1), Err(1));
+        assert_eq!(consumer.read(), None);
+        let (mut producer, mut consumer) = RingBuffer::new(1).split();
+        assert_eq!(producer.write(1), Err(1));
+        assert_eq!(consumer.read(), None);
+    }
+
+    #[test]
+    fn test_drops_remaining_items() {
+        let item = Arc::new(());
+        let (mut producer, consumer) = RingBuffer::new(4).split();
+        for _ in 0..3 {
+            assert!(producer.write(Arc::clone(&item)).is_ok());
+        }
+        drop(consumer);
+        assert_eq!(Arc::strong_count(&item), 4);
+        drop(producer);
+        assert_eq!(Arc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn test_stress() {
+        const ITEMS: usize = 1_000_000;
+        let (mut producer, mut consumer) = RingBuffer::new(64).split();
+        thread::scope(|s| {
+            s.spawn(move || {
+                for i in 0..ITEMS {
+                    let mut item = Box::new(i);
+                    while let Err(rejected) = producer.write(item) {
+                        item = rejected;
+                        thread::yield_now();
+                    }
+                }
+            });
+            s.spawn(move || {
+                // Every item must arrive exactly once, in order
+                let mut expected = 0;
+                while expected < ITEMS {
+                    match consumer.read() {
+                        Some(item) => {
+                            assert_eq!(*item, expected);
+                            expected += 1;
+                        }
+                        None => thread::yield_now(),
+                    }
+                }
+                assert_eq!(consumer.read(), None);
+            });
+        });
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use crate::RingBuffer;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    /// Sends `items` items through a buffer of `size` slots, and checks that
+    /// each of them arrives exactly once, in order
+    fn send_items(size: usize, items: usize) {
+        loom::model(move || {
+            let (mut producer, mut consumer) = RingBuffer::new(size).split();
+            let handle = thread::spawn(move || {
+                for i in 0..items {
+                    let mut item = i;
+                    while let Err(rejected) = producer.write(item) {
+                        item = rejected;
+                        thread::yield_now();
+                    }
+                }
+            });
+            let mut received = Vec::new();
+            while received.len() < items {
+                match consumer.read() {
+                    Some(item) => received.push(item),
+                    None => thread::yield_now(),
+                }
+            }
+            handle.join().unwrap();
+            assert_eq!(received, (0..items).collect::<Vec<_>>());
+            assert_eq!(consumer.read(), None);
+        });
+    }
+
+    #[test]
+    fn loom_single_slot_item() {
+        send_items(2, 3);
+    }
+
+    #[test]
+    fn loom_wrap_around() {
+        send_items(3, 4);
+    }
+
+    #[test]
+    fn loom_drops_items_left_behind() {
+        // loom reports an error if any of the `Arc`s is leaked
+        loom::model(|| {
+            let (mut producer, mut consumer) = RingBuffer::new(3).split();
+            let handle = thread::spawn(move || {
+                let _ = producer.write(Arc::new(1));
+                let _ = producer.write(Arc::new(2));
+            });
+            drop(consumer.read());
+            handle.join().unwrap();
+        });
+    }
+}
@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use boxed_data::{compile, eval, parse, Env, Vm};
+
+/// Evaluates a couple of expressions for each `x` in `0..1000`, with the recursive [eval]
+/// and with a compiled program on a [Vm]
+fn bench_eval_vs_vm(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eval_vs_vm");
+    for text in [
+        "x * x - 3 * x + 2",
+        "(x * x - 3 * x + 2) / (x + 1) + sum(x, 2 * x, 3 * x, 4 * x)",
+        "let y = x * x + 1 in let z = y * y - x in sum(y, z, z / y, y - z)",
+    ] {
+        let expr = parse(text).unwrap();
+        group.bench_with_input(BenchmarkId::new("eval", text), &expr, |b, expr| {
+            let mut env = Env::new();
+            b.iter(|| {
+                (0..1000)
+                    .map(|x| {
+                        env.set("x", x);
+                        eval(expr, &env).unwrap()
+                    })
+                    .fold(0, i64::wrapping_add)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("vm", text), &expr, |b, expr| {
+            let program = compile(expr);
+            let mut vm = Vm::new();
+            b.iter(|| {
+                (0..1000)
+                    .map(|x| vm.run(&program, &[x]).unwrap())
+                    .fold(0, i64::wrapping_add)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_eval_vs_vm);
+criterion_main!(benches);
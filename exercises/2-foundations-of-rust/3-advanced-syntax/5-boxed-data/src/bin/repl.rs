@@ -0,0 +1,191 @@
+//! A REPL to evaluate expressions without recompiling. Run it with `cargo run --bin repl`,
+//! and type `:help` to see what it can do.
+//!
+//! When the input is not a terminal, no prompt is printed, so a script can be piped in:
+//!
+//! ```text
+//! $ printf 'let x = 6\nx * 7\n' | cargo run -q --bin repl
+//! x = 6
+//! 42
+//! ```
+//!
+//! Errors are printed along with the other output, so that it reads as a transcript.
+//! The exit status is 1 if any of the lines failed.
+
+use std::{
+    fmt,
+    io::{self, BufRead, IsTerminal, Write},
+    process::ExitCode,
+};
+
+use boxed_data::{eval, parse, simplify, Env, EvalError, Expr, ParseError};
+
+const HELP: &str = "\
+<expr>            evaluate an expression, such as `(x - 5) * 3 + sum(x, 1, 2)`
+let <name> = <expr>
+                  evaluate an expression, and assign it to a variable for the next lines
+:ast <expr>       print the syntax tree of an expression
+:simplify <expr>  print the simplified expression
+:vars             print the values of the variables
+:history          print the lines entered so far
+:help             print this help
+:quit             quit, just like end of input";
+
+/// The reasons a line can fail
+#[derive(Debug)]
+enum ReplError {
+    /// The expression in `input` could not be parsed
+    Parse {
+        input: String,
+        error: ParseError,
+    },
+    Eval(EvalError),
+    UnknownCommand(String),
+}
+
+impl fmt::Display for ReplError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplError::Parse { input, error } => {
+                // Point at the offending token below the input
+                let column = input[..error.offset].chars().count();
+                write!(f, "{error}\n  {input}\n  {:column$}^", "")
+            }
+            ReplError::Eval(error) => write!(f, "{error}"),
+            ReplError::UnknownCommand(command) => {
+                write!(f, "unknown command `{command}`, type `:help` for help")
+            }
+        }
+    }
+}
+
+impl From<EvalError> for ReplError {
+    fn from(error: EvalError) -> Self {
+        ReplError::Eval(error)
+    }
+}
+
+enum Response {
+    Output(String),
+    Quit,
+}
+
+#[derive(Default)]
+struct Repl {
+    /// The variables assigned so far
+    env: Env,
+    /// The lines entered so far, except for empty ones
+    history: Vec<String>,
+}
+
+impl Repl {
+    fn handle(&mut self, line: &str) -> Result<Response, ReplError> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(Response::Output(String::new()));
+        }
+        self.history.push(line.to_string());
+
+        let output = if let Some(command) = line.strip_prefix(':') {
+            let (command, arg) = command.split_once(' ').unwrap_or((command, ""));
+            match command {
+                "ast" => format!("{:?}", parse_input(arg)?),
+                "simplify" => simplify(&parse_input(arg)?).to_string(),
+                "vars" => {
+                    let mut vars: Vec<_> = self.env.iter().collect();
+                    vars.sort();
+                    let vars: Vec<_> = vars
+                        .into_iter()
+                        .map(|(name, value)| format!("{name} = {value}"))
+                        .collect();
+                    vars.join("\n")
+                }
+                "history" => {
+                    let lines: Vec<_> = self
+                        .history
+                        .iter()
+                        .enumerate()
+                        .map(|(i, line)| format!("{:>4}  {line}", i + 1))
+                        .collect();
+                    lines.join("\n")
+                }
+                "help" => HELP.to_string(),
+                "quit" => return Ok(Response::Quit),
+                _ => return Err(ReplError::UnknownCommand(format!(":{command}"))),
+            }
+        } else if let Some((name, value)) = assignment(line) {
+            let value = eval(&value, &self.env)?;
+            self.env.set(name, value);
+            format!("{name} = {value}")
+        } else {
+            eval(&parse_input(line)?, &self.env)?.to_string()
+        };
+        Ok(Response::Output(output))
+    }
+}
+
+fn parse_input(input: &str) -> Result<Expr, ReplError> {
+    let input = input.trim();
+    parse(input).map_err(|error| ReplError::Parse {
+        input: input.to_string(),
+        error,
+    })
+}
+
+/// Splits `let name = value` into the name and the value. A `let` with an `in` is not
+/// an assignment, but a `Let` expression, so the value must be an expression by itself.
+fn assignment(line: &str) -> Option<(&str, Expr)> {
+    let rest = line.strip_prefix("let")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let (name, value) = rest.split_once('=')?;
+    let name = name.trim();
+    // Only a valid name parses as a variable
+    if !matches!(parse(name), Ok(Expr::Var(var)) if var == name) {
+        return None;
+    }
+    let value = parse(value).ok()?;
+    Some((name, value))
+}
+
+fn main() -> ExitCode {
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    if interactive {
+        println!("Type an expression, `let x = ...` or `:help`");
+    }
+
+    let mut repl = Repl::default();
+    let mut failed = false;
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("> ");
+            io::stdout().flush().unwrap();
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(e)) => {
+                eprintln!("failed to read input: {e}");
+                return ExitCode::FAILURE;
+            }
+            None => break,
+        };
+        match repl.handle(&line) {
+            Ok(Response::Output(output)) if output.is_empty() => {}
+            Ok(Response::Output(output)) => println!("{output}"),
+            Ok(Response::Quit) => break,
+            Err(e) => {
+                println!("error: {e}");
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
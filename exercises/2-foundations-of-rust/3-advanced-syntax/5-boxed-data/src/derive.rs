@@ -0,0 +1,244 @@
+//! Symbolic differentiation of an [`Expr`] with respect to one of its variables.
+
+use crate::{add, div, let_in, mul, product, sigma, simplify, sub, var, Const, Expr};
+
+/// Returns the derivative of `expr` with respect to the variable `x`, simplified with [`simplify`].
+///
+/// The derivative is taken as if `expr` was a function on the real numbers, so `Div` uses the
+/// quotient rule, and evaluating the result with [`eval`](crate::eval) rounds just like
+/// evaluating `expr` does. For the same reason, the derivative may overflow where `expr` does not.
+///
+/// The bounds of a `Sigma` or `Product` are whole numbers, so they are taken to be constant.
+pub fn derive(expr: &Expr, x: &str) -> Expr {
+    simplify(&derive_unsimplified(expr, x))
+}
+
+fn derive_unsimplified(expr: &Expr, x: &str) -> Expr {
+    use Expr::*;
+    let d = |e: &Expr| derive_unsimplified(e, x);
+    match expr {
+        Const(_) => Const(0),
+        Var(name) => Const(i64::from(name == x)),
+        Add(f, g) => add(d(f), d(g)),
+        Sub(f, g) => sub(d(f), d(g)),
+        // (f * g)' = f' * g + f * g'
+        Mul(f, g) => add(mul(d(f), (**g).clone()), mul((**f).clone(), d(g))),
+        // (f / g)' = (f' * g - f * g') / g^2
+        Div(f, g) => div(
+            sub(mul(d(f), (**g).clone()), mul((**f).clone(), d(g))),
+            mul((**g).clone(), (**g).clone()),
+        ),
+        Summation(exprs) => Summation(exprs.iter().map(d).collect()),
+        Let(y, value, body) => derive_let(y, value, body, x),
+        // The loop variable shadows `x` in the body, which then doesn't depend on `x` at all
+        Sigma(i, _, _, _) | Product(i, _, _, _) if i == x => Const(0),
+        Sigma(i, from, to, body) => sigma(i, (**from).clone(), (**to).clone(), d(body)),
+        Product(i, from, to, body) => derive_product(i, from, to, body, x),
+    }
+}
+
+/// Derives `let y = value in body` with the chain rule: the body depends on `x` directly, unless `y`
+/// shadows it, and through `y`. That gives `let y = value in (dbody/dx + dbody/dy * dvalue/dx)`,
+/// except that `dvalue/dx` must be evaluated outside of the `Let`, where `y` still has its outer value.
+fn derive_let(y: &str, value: &Expr, body: &Expr, x: &str) -> Expr {
+    let direct = if y == x {
+        Const(0)
+    } else {
+        derive_unsimplified(body, x)
+    };
+    let via_y = simplify(&derive_unsimplified(body, y));
+    let dvalue = simplify(&derive_unsimplified(value, x));
+    if via_y == Const(0) || dvalue == Const(0) {
+        // The body doesn't depend on `x` through `y`
+        return let_in(y, value.clone(), direct);
+    }
+
+    // Bind `dvalue/dx` to a variable that doesn't capture any of the variables of the derivative,
+    // including those of `value`, which is evaluated with it bound
+    let dy = fresh_name(format!("d{y}"), y, &[value, &direct, &via_y]);
+    let_in(
+        &dy,
+        dvalue,
+        let_in(y, value.clone(), add(direct, mul(via_y, var(&dy)))),
+    )
+}
+
+/// Derives the product of `f(i)` for `i` in `from..=to` with the product rule, which gives the sum
+/// over `k` of `f(from) * ... * f(k - 1) * f'(k) * f(k + 1) * ... * f(to)`.
+fn derive_product(i: &str, from: &Expr, to: &Expr, f: &Expr, x: &str) -> Expr {
+    let df = derive_unsimplified(f, x);
+    // The bounds and `f` are evaluated with `k` bound, so it mustn't capture any of their variables
+    let k = fresh_name("k".to_string(), i, &[from, to, f, &df]);
+    let before = product(i, from.clone(), sub(var(&k), Const(1)), f.clone());
+    let after = product(i, add(var(&k), Const(1)), to.clone(), f.clone());
+    sigma(
+        &k,
+        from.clone(),
+        to.clone(),
+        mul(mul(before, let_in(i, var(&k), df)), after),
+    )
+}
+
+/// Returns `name` with as many `'`s appended as needed to make it differ from `bound`,
+/// and from the free variables of `exprs`
+fn fresh_name(mut name: String, bound: &str, exprs: &[&Expr]) -> String {
+    while name == bound || exprs.iter().any(|e| e.free_vars().contains(name.as_str())) {
+        name.push('\'');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        add, derive, div, eval, let_in, mul, parse, product, sigma, var, Const, Env, Expr,
+        Summation,
+    };
+
+    /// Evaluates `expr` on the real numbers, which is what `derive` computes the derivative for
+    fn eval_f64(expr: &Expr, env: &Env<f64>) -> f64 {
+        eval(&expr.cast(), env).unwrap()
+    }
+
+    /// Checks the derivative of `expr` with respect to each of its free variables against
+    /// the central finite difference, at a couple of points
+    fn check_against_finite_differences(expr: &Expr) {
+        const H: f64 = 1e-6;
+        let names: Vec<&str> = expr.free_vars().into_iter().collect();
+        for point in [[0.5, 1.5, -2.0], [3.0, -0.25, 0.75], [-1.25, 2.0, 4.5]] {
+            let env: Env<f64> = names.iter().copied().zip(point).collect();
+            for name in &names {
+                let shifted = |delta| {
+                    let mut env = env.clone();
+                    env.set(name, env.get(name).unwrap() + delta);
+                    eval_f64(expr, &env)
+                };
+                let expected = (shifted(H) - shifted(-H)) / (2.0 * H);
+                let actual = eval_f64(&derive(expr, name), &env);
+                assert!(
+                    (actual - expected).abs() <= 1e-4 * expected.abs().max(1.0),
+                    "d/d{name} of {expr:?} at {env:?} is {actual}, expected {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_derive() {
+        let x = || var("x");
+        assert_eq!(derive(&Const(5), "x"), Const(0));
+        assert_eq!(derive(&x(), "x"), Const(1));
+        assert_eq!(derive(&var("y"), "x"), Const(0));
+        assert_eq!(derive(&add(mul(Const(3), x()), Const(2)), "x"), Const(3));
+        assert_eq!(derive(&mul(x(), x()), "x"), add(x(), x()));
+        assert_eq!(derive(&mul(x(), var("y")), "y"), x());
+        assert_eq!(
+            derive(&div(Const(1), x()), "x"),
+            div(Const(-1), mul(x(), x()))
+        );
+        assert_eq!(
+            derive(&Summation(vec![x(), mul(Const(2), x()), var("y")]), "x"),
+            Const(3)
+        );
+    }
+
+    #[test]
+    fn test_finite_differences() {
+        for text in [
+            "x * x * x - 4 * x + 7",
+            "x * y - y / x",
+            "(x + 1) / (y * y + 1)",
+            "sum(x, x * y, y * y * z, 3)",
+            "x / y / z",
+            "(x - y) * (x - y) + (y - z) * (y - z)",
+        ] {
+            check_against_finite_differences(&parse(text).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_let() {
+        // let y = x * x in y * y + x
+        let expr = let_in(
+            "y",
+            mul(var("x"), var("x")),
+            add(mul(var("y"), var("y")), var("x")),
+        );
+        check_against_finite_differences(&expr);
+
+        // The `x` in the body refers to the `Let`, so only depends on the outer `x` through its value
+        // let x = x * y in x * x
+        let expr = let_in("x", mul(var("x"), var("y")), mul(var("x"), var("x")));
+        check_against_finite_differences(&expr);
+
+        // The name used for the derivative of the value doesn't capture a `dy` in the body
+        // let y = x * x in y * dy
+        let expr = let_in("y", mul(var("x"), var("x")), mul(var("y"), var("dy")));
+        check_against_finite_differences(&expr);
+
+        // Nor a `dy` in the value
+        // let y = x + dy in y * y
+        let expr = let_in("y", add(var("x"), var("dy")), mul(var("y"), var("y")));
+        check_against_finite_differences(&expr);
+        assert_eq!(
+            derive(&expr, "x").to_string(),
+            "let dy' = 1 in let y = x + dy in (y + y) * dy'"
+        );
+
+        for text in [
+            "let y = x + z in let z = y * y in z / x",
+            "let x = x * x in let x = x * x in x + y",
+        ] {
+            check_against_finite_differences(&parse(text).unwrap());
+        }
+
+        // A `Let` that the derivative doesn't need is simplified away
+        let expr = let_in(
+            "y",
+            mul(var("z"), var("z")),
+            add(var("y"), mul(Const(2), var("x"))),
+        );
+        assert_eq!(derive(&expr, "x"), Const(2));
+    }
+
+    #[test]
+    fn test_sigma_product() {
+        for text in [
+            "sigma(i, 1, 4, x * i * i + y / i)",
+            "product(i, 1, 3, x + i * y)",
+            "product(i, -2, 2, x * x - i)",
+            "product(i, 1, 3, sigma(j, 1, i, x * j + y))",
+            // `k` is used in the body, so the derivative needs another name for the product rule
+            "product(i, 1, 3, x * k + i)",
+            // The inner `x` is the loop variable, not the outer one
+            "sigma(i, 1, 3, product(x, 1, i, x) * x + y)",
+        ] {
+            check_against_finite_differences(&parse(text).unwrap());
+        }
+
+        let i = || var("i");
+        // The derivative of a constant factor is the sum of the factors
+        assert_eq!(
+            derive(&sigma("i", Const(1), var("n"), mul(i(), var("x"))), "x"),
+            sigma("i", Const(1), var("n"), i())
+        );
+        assert_eq!(
+            derive(&product("x", Const(1), Const(3), var("x")), "x"),
+            Const(0)
+        );
+        assert_eq!(
+            derive(&product("i", Const(1), Const(0), var("x")), "x"),
+            Const(0)
+        );
+    }
+
+    #[test]
+    fn test_gradient() {
+        // The squared error of a line through a single point, (a * x + b - y)^2
+        let cost = parse("(a * x + b - y) * (a * x + b - y)").unwrap();
+        let env = crate::Env::from_iter([("a", 2), ("b", 1), ("x", 3), ("y", 4)]);
+        // The error is 2 * 3 + 1 - 4 = 3, so the gradient is (2 * 3 * x, 2 * 3)
+        assert_eq!(crate::eval(&derive(&cost, "a"), &env), Ok(18));
+        assert_eq!(crate::eval(&derive(&cost, "b"), &env), Ok(6));
+    }
+}
@@ -0,0 +1,758 @@
+//! A data type modelling the abstract syntax tree of an arithmetic expression, and an evaluator for it.
+//!
+//! An [`Expr`] is built from constants, addition, subtraction, multiplication and division, and
+//! `Summation(Vec<Expr>)`, the sum of a list of expressions. The operands of the binary operators are boxed,
+//! because an `Expr` can't contain an `Expr` directly, but the items of a `Summation` aren't: a `Vec` already
+//! keeps its items on the heap. [`eval`] returns a `Result` that reports division by zero and overflow
+//! as an [`EvalError`], which is propagated out of nested expressions with `?`.
+//!
+//! An `Expr` is generic over the type of its constants, which defaults to `i64`. It can be evaluated with
+//! any type that implements [`Number`]: checked `i64`s, `f64`s or exact [`Rational`]s. [`Expr::cast`] converts
+//! the constants of an `Expr<i64>`, to evaluate the same expression with another type.
+//! The other tools in this crate only work with `Expr<i64>`: the parser and `Display` only know whole
+//! numbers, and [`simplify`], [`derive`](fn@derive) and the [`Vm`] compute with `i64`s, like `eval` does for them.
+//!
+//! A `Summation` and a `Sigma` of the same values give the same sum or error, which depends on the type:
+//! an `i64` sum only needs to fit as a whole, so that the order of the values doesn't matter,
+//! while `f64`s and `Rational`s are added one by one. See [`Number::try_sum`].
+//!
+//! An `Expr` can contain any number of named variables, of which the values are looked up
+//! in an [`Env`] during evaluation. `Let` binds a variable to a value within an expression,
+//! and [`Expr::free_vars`] lists the variables that need a value in the `Env`.
+//! `Sigma` and `Product` bind a loop variable to each number in a range, and add up or
+//! multiply the values of their body, like `\sum_{i = 1}^{5} i` and `\prod_{i = 1}^{5} i` in LaTeX.
+//!
+//! Expressions that are generated programmatically can be cleaned up with [`simplify`],
+//! and [`derive`](fn@derive) computes the derivative of an expression with respect to one of its variables.
+//! To evaluate the same expression many times, [`compile`] it into a [`Program`] for a [`Vm`].
+//!
+//! Instead of building an `Expr` with the helper functions, you can also [`parse`] one from text,
+//! such as `"(x - 5) * 3 + sum(x, 1, 2)"`. Printing an `Expr` with `{}` gives text in the same syntax,
+//! which parses back into the same `Expr`. To try out expressions without recompiling,
+//! run the REPL in `src/bin/repl.rs` with `cargo run --bin repl`.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fmt,
+};
+
+mod derive;
+mod number;
+mod parser;
+mod printer;
+mod simplify;
+#[cfg(test)]
+mod testing;
+mod vm;
+
+pub use derive::derive;
+pub use number::{Number, Rational};
+pub use parser::{parse, ParseError};
+pub use simplify::simplify;
+pub use vm::{compile, Instr, Program, Vm};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Expr<N = i64> {
+    Const(N),
+    Add(Box<Expr<N>>, Box<Expr<N>>),
+    Sub(Box<Expr<N>>, Box<Expr<N>>),
+    Mul(Box<Expr<N>>, Box<Expr<N>>),
+    Div(Box<Expr<N>>, Box<Expr<N>>),
+    Var(String),
+    Summation(Vec<Expr<N>>),
+    /// `Let(name, value, body)` evaluates `body` with the variable `name` bound to `value`
+    Let(String, Box<Expr<N>>, Box<Expr<N>>),
+    /// `Sigma(name, from, to, body)` is the sum of `body` for each value of the variable `name`
+    /// in `from..=to`, or 0 if the range is empty
+    Sigma(String, Box<Expr<N>>, Box<Expr<N>>, Box<Expr<N>>),
+    /// `Product(name, from, to, body)` is the product of `body` for each value of the variable `name`
+    /// in `from..=to`, or 1 if the range is empty
+    Product(String, Box<Expr<N>>, Box<Expr<N>>, Box<Expr<N>>),
+}
+
+// inject these identifiers directly into the current namespace
+pub use Expr::Const;
+pub use Expr::Summation;
+pub use Expr::Var;
+
+// These are convenience functions, so you don't have to type "Box::new" as often
+// when building test-data types
+pub fn add<N>(x: Expr<N>, y: Expr<N>) -> Expr<N> {
+    Expr::Add(Box::new(x), Box::new(y))
+}
+
+pub fn sub<N>(x: Expr<N>, y: Expr<N>) -> Expr<N> {
+    Expr::Sub(Box::new(x), Box::new(y))
+}
+
+pub fn mul<N>(x: Expr<N>, y: Expr<N>) -> Expr<N> {
+    Expr::Mul(Box::new(x), Box::new(y))
+}
+
+pub fn div<N>(x: Expr<N>, y: Expr<N>) -> Expr<N> {
+    Expr::Div(Box::new(x), Box::new(y))
+}
+
+pub fn var<N>(name: &str) -> Expr<N> {
+    Expr::Var(name.to_string())
+}
+
+pub fn let_in<N>(name: &str, value: Expr<N>, body: Expr<N>) -> Expr<N> {
+    Expr::Let(name.to_string(), Box::new(value), Box::new(body))
+}
+
+pub fn sigma<N>(name: &str, from: Expr<N>, to: Expr<N>, body: Expr<N>) -> Expr<N> {
+    Expr::Sigma(
+        name.to_string(),
+        Box::new(from),
+        Box::new(to),
+        Box::new(body),
+    )
+}
+
+pub fn product<N>(name: &str, from: Expr<N>, to: Expr<N>, body: Expr<N>) -> Expr<N> {
+    Expr::Product(
+        name.to_string(),
+        Box::new(from),
+        Box::new(to),
+        Box::new(body),
+    )
+}
+
+impl<N> Expr<N> {
+    /// Returns the names of the variables that need a value in the [`Env`] to evaluate this expression,
+    /// i.e. the variables that are not bound by a surrounding `Let`
+    pub fn free_vars(&self) -> BTreeSet<&str> {
+        let mut free = BTreeSet::new();
+        self.collect_free_vars(&mut Vec::new(), &mut free);
+        free
+    }
+
+    /// Adds the free variables to `free`, where `bound` are the names bound by the surrounding `Let`s
+    fn collect_free_vars<'a>(&'a self, bound: &mut Vec<&'a str>, free: &mut BTreeSet<&'a str>) {
+        use Expr::*;
+        match self {
+            Const(_) => {}
+            Var(name) => {
+                if !bound.contains(&name.as_str()) {
+                    free.insert(name);
+                }
+            }
+            Add(lhs, rhs) | Sub(lhs, rhs) | Mul(lhs, rhs) | Div(lhs, rhs) => {
+                lhs.collect_free_vars(bound, free);
+                rhs.collect_free_vars(bound, free);
+            }
+            Summation(exprs) => {
+                for e in exprs {
+                    e.collect_free_vars(bound, free);
+                }
+            }
+            Let(name, value, body) => {
+                // The variable is only bound in the body, not in its own value
+                value.collect_free_vars(bound, free);
+                bound.push(name);
+                body.collect_free_vars(bound, free);
+                bound.pop();
+            }
+            Sigma(name, from, to, body) | Product(name, from, to, body) => {
+                // The loop variable is only bound in the body, not in the bounds of the range
+                from.collect_free_vars(bound, free);
+                to.collect_free_vars(bound, free);
+                bound.push(name);
+                body.collect_free_vars(bound, free);
+                bound.pop();
+            }
+        }
+    }
+}
+
+impl Expr<i64> {
+    /// Converts the constants to another [`Number`] type, to evaluate the same expression with it
+    pub fn cast<M: Number>(&self) -> Expr<M> {
+        use Expr::*;
+        let cast = |e: &Expr| Box::new(e.cast());
+        match self {
+            Const(k) => Const(M::from_i64(*k)),
+            Var(name) => Var(name.clone()),
+            Add(lhs, rhs) => Add(cast(lhs), cast(rhs)),
+            Sub(lhs, rhs) => Sub(cast(lhs), cast(rhs)),
+            Mul(lhs, rhs) => Mul(cast(lhs), cast(rhs)),
+            Div(lhs, rhs) => Div(cast(lhs), cast(rhs)),
+            Summation(exprs) => Summation(exprs.iter().map(Expr::cast).collect()),
+            Let(name, value, body) => Let(name.clone(), cast(value), cast(body)),
+            Sigma(name, from, to, body) => Sigma(name.clone(), cast(from), cast(to), cast(body)),
+            Product(name, from, to, body) => {
+                Product(name.clone(), cast(from), cast(to), cast(body))
+            }
+        }
+    }
+}
+
+/// The values of the variables an [`Expr`] is evaluated with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Env<N = i64> {
+    vars: HashMap<String, N>,
+}
+
+impl<N: Copy> Env<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `name` to `value`, and returns the value it was bound to before, if any
+    pub fn set(&mut self, name: &str, value: N) -> Option<N> {
+        self.vars.insert(name.to_string(), value)
+    }
+
+    pub fn get(&self, name: &str) -> Option<N> {
+        self.vars.get(name).copied()
+    }
+
+    /// Unbinds `name`, and returns the value it was bound to, if any
+    pub fn remove(&mut self, name: &str) -> Option<N> {
+        self.vars.remove(name)
+    }
+
+    /// Returns the variables and their values, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, N)> {
+        self.vars
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+    }
+}
+
+// Deriving `Default` would require `N: Default`, which an `Env` doesn't need
+impl<N> Default for Env<N> {
+    fn default() -> Self {
+        Env {
+            vars: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, N> FromIterator<(&'a str, N)> for Env<N> {
+    fn from_iter<I: IntoIterator<Item = (&'a str, N)>>(iter: I) -> Self {
+        Env {
+            vars: iter
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }
+    }
+}
+
+// ...
+
+/// The reasons evaluating an [`Expr`] can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// The right-hand side of a division evaluated to zero
+    DivisionByZero,
+    /// The result of an operation can't be represented by the [`Number`] type
+    Overflow,
+    /// A variable with the given name has no value
+    UnboundVariable(String),
+    /// A bound of the range of a `Sigma` or `Product` is not a whole number that fits in an `i64`
+    NonIntegerBound,
+    /// The ranges of the `Sigma`s and `Product`s take more than [`MAX_ITERATIONS`] iterations in total
+    TooManyIterations,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::Overflow => write!(f, "arithmetic overflow"),
+            EvalError::UnboundVariable(name) => write!(f, "unbound variable `{name}`"),
+            EvalError::NonIntegerBound => write!(f, "the bound of a range is not a whole number"),
+            EvalError::TooManyIterations => {
+                write!(f, "the ranges take more than {MAX_ITERATIONS} iterations")
+            }
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+/// The maximum number of times the bodies of the `Sigma`s and `Product`s in an expression are
+/// evaluated in total, so that an expression like `sum(i, 0, 1000000000000000000, 0)` fails
+/// instead of running for ages
+pub const MAX_ITERATIONS: u64 = 10_000_000;
+
+/// Adds the length of the range `from..=to` to the number of `iterations` so far, before the range
+/// is run, so that a range that is too long fails right away
+pub(crate) fn count_iterations(iterations: &mut u64, from: i64, to: i64) -> Result<(), EvalError> {
+    let len = (i128::from(to) - i128::from(from) + 1).max(0);
+    match u64::try_from(len) {
+        Ok(len) if len <= MAX_ITERATIONS - *iterations => {
+            *iterations += len;
+            Ok(())
+        }
+        _ => Err(EvalError::TooManyIterations),
+    }
+}
+
+/// Evaluates `expr`, looking up the values of its free variables in `env`
+pub fn eval<N: Number>(expr: &Expr<N>, env: &Env<N>) -> Result<N, EvalError> {
+    eval_in(expr, &mut Scope::new(env))
+}
+
+/// The variables during evaluation: those bound by the surrounding `Let`s and loops,
+/// in front of those in the `Env`
+struct Scope<'a, N> {
+    env: &'a Env<N>,
+    /// The innermost binding is last, and shadows the others
+    locals: Vec<(&'a str, N)>,
+    /// The number of iterations of the ranges so far, up to [`MAX_ITERATIONS`]
+    iterations: u64,
+}
+
+impl<'a, N: Copy> Scope<'a, N> {
+    fn new(env: &'a Env<N>) -> Self {
+        Scope {
+            env,
+            locals: Vec::new(),
+            iterations: 0,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<N> {
+        match self.locals.iter().rev().find(|(local, _)| *local == name) {
+            Some(&(_, value)) => Some(value),
+            None => self.env.get(name),
+        }
+    }
+
+    /// Calls `f` with `name` bound to `value`, which `f` may change with [`Scope::rebind`]
+    fn bind<T>(&mut self, name: &'a str, value: N, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.locals.push((name, value));
+        let result = f(self);
+        self.locals.pop();
+        result
+    }
+
+    /// Changes the value of the innermost binding
+    fn rebind(&mut self, value: N) {
+        self.locals.last_mut().expect("no variable is bound").1 = value;
+    }
+}
+
+fn eval_in<'a, N: Number>(expr: &'a Expr<N>, scope: &mut Scope<'a, N>) -> Result<N, EvalError> {
+    use Expr::*;
+    match expr {
+        Const(k) => Ok(*k),
+        Var(name) => scope
+            .get(name)
+            .ok_or_else(|| EvalError::UnboundVariable(name.clone())),
+        Add(lhs, rhs) => eval_in(lhs, scope)?.try_add(eval_in(rhs, scope)?),
+        Sub(lhs, rhs) => eval_in(lhs, scope)?.try_sub(eval_in(rhs, scope)?),
+        Mul(lhs, rhs) => eval_in(lhs, scope)?.try_mul(eval_in(rhs, scope)?),
+        Div(lhs, rhs) => eval_in(lhs, scope)?.try_div(eval_in(rhs, scope)?),
+        Summation(exprs) => N::try_sum(exprs.iter().map(|e| eval_in(e, scope))),
+        Let(name, value, body) => {
+            let value = eval_in(value, scope)?;
+            scope.bind(name, value, |scope| eval_in(body, scope))
+        }
+        // A `Sigma` adds up its values like a `Summation` does, so the same values overflow the same way
+        Sigma(name, from, to, body) => {
+            fold_range(name, from, to, body, scope, |values| N::try_sum(values))
+        }
+        Product(name, from, to, body) => fold_range(name, from, to, body, scope, |values| {
+            let mut acc = N::from_i64(1);
+            for value in values {
+                acc = acc.try_mul(value?)?;
+            }
+            Ok(acc)
+        }),
+    }
+}
+
+/// Evaluates `body` for each value of `name` in `from..=to`, and combines the values with `fold`
+fn fold_range<'a, N: Number>(
+    name: &'a str,
+    from: &'a Expr<N>,
+    to: &'a Expr<N>,
+    body: &'a Expr<N>,
+    scope: &mut Scope<'a, N>,
+    fold: impl FnOnce(&mut dyn Iterator<Item = Result<N, EvalError>>) -> Result<N, EvalError>,
+) -> Result<N, EvalError> {
+    let bound = |value: N| value.to_i64().ok_or(EvalError::NonIntegerBound);
+    let from = bound(eval_in(from, scope)?)?;
+    let to = bound(eval_in(to, scope)?)?;
+    count_iterations(&mut scope.iterations, from, to)?;
+    scope.bind(name, N::from_i64(from), |scope| {
+        fold(&mut (from..=to).map(|i| {
+            scope.rebind(N::from_i64(i));
+            eval_in(body, scope)
+        }))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cases() {
+        let env = Env::from_iter([("x", 42)]);
+        let x = || var("x");
+        assert_eq!(eval(&Const(5), &env), Ok(5));
+        assert_eq!(eval(&x(), &env), Ok(42));
+        assert_eq!(eval(&sub(x(), Const(5)), &env), Ok(37));
+        assert_eq!(eval(&sub(x(), x()), &env), Ok(0));
+        assert_eq!(eval(&add(sub(x(), Const(5)), Const(5)), &env), Ok(42));
+        assert_eq!(eval(&Summation(vec![x(), Const(1)]), &env), Ok(43));
+        assert_eq!(eval(&mul(x(), Const(2)), &env), Ok(84));
+        assert_eq!(eval(&div(x(), Const(5)), &env), Ok(8));
+    }
+
+    #[test]
+    fn test_errors() {
+        let x = |value| Env::from_iter([("x", value)]);
+        assert_eq!(
+            eval(&div(Const(1), sub(var("x"), var("x"))), &x(3)),
+            Err(EvalError::DivisionByZero)
+        );
+        assert_eq!(
+            eval(&add(var("x"), Const(1)), &x(i64::MAX)),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            eval(&sub(var("x"), Const(1)), &x(i64::MIN)),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            eval(&mul(var("x"), var("x")), &x(1 << 32)),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            eval(&div(var("x"), Const(-1)), &x(i64::MIN)),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            eval(
+                &Summation(vec![var("x"), var("x"), Const(-5)]),
+                &x(i64::MAX)
+            ),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            eval(
+                &Summation(vec![var("x"), Const(1), Const(-2)]),
+                &x(i64::MAX)
+            ),
+            Ok(i64::MAX - 1)
+        );
+        // Errors are propagated out of nested expressions
+        assert_eq!(
+            eval(
+                &Summation(vec![Const(1), mul(Const(2), div(var("x"), Const(0)))]),
+                &x(1)
+            ),
+            Err(EvalError::DivisionByZero)
+        );
+        assert_eq!(
+            eval(&add(var("x"), var("y")), &x(1)),
+            Err(EvalError::UnboundVariable("y".to_string()))
+        );
+        assert_eq!(EvalError::DivisionByZero.to_string(), "division by zero");
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut env = Env::new();
+        env.set("width", 3);
+        env.set("height", 4);
+        let area = mul(var("width"), var("height"));
+        assert_eq!(eval(&area, &env), Ok(12));
+        assert_eq!(env.set("width", 5), Some(3));
+        assert_eq!(eval(&area, &env), Ok(20));
+        assert_eq!(env.remove("height"), Some(4));
+        assert_eq!(
+            eval(&area, &env),
+            Err(EvalError::UnboundVariable("height".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_let() {
+        let env = Env::from_iter([("x", 1)]);
+        // let y = x + 1 in y * y
+        let expr = let_in("y", add(var("x"), Const(1)), mul(var("y"), var("y")));
+        assert_eq!(eval(&expr, &env), Ok(4));
+
+        // A `Let` shadows the variable only in its body
+        // x + (let x = 10 in x) + x
+        let expr = add(add(var("x"), let_in("x", Const(10), var("x"))), var("x"));
+        assert_eq!(eval(&expr, &env), Ok(12));
+
+        // let x = x + 1 in (let x = x * 10 in x)
+        let expr = let_in(
+            "x",
+            add(var("x"), Const(1)),
+            let_in("x", mul(var("x"), Const(10)), var("x")),
+        );
+        assert_eq!(eval(&expr, &env), Ok(20));
+        assert_eq!(env.get("x"), Some(1));
+    }
+
+    #[test]
+    fn test_free_vars() {
+        let expr: Expr = add(mul(var("b"), var("a")), var("b"));
+        assert_eq!(expr.free_vars(), BTreeSet::from(["a", "b"]));
+        assert!(Const(1).free_vars().is_empty());
+
+        // `x` is bound in the body of the `Let`, but not in its value
+        let expr: Expr = let_in("x", add(var("x"), var("y")), mul(var("x"), var("z")));
+        assert_eq!(expr.free_vars(), BTreeSet::from(["x", "y", "z"]));
+        let expr = let_in("x", Const(1), mul(var("x"), var("z")));
+        assert_eq!(expr.free_vars(), BTreeSet::from(["z"]));
+
+        // The loop variable is bound in the body, but not in the bounds
+        let expr: Expr = sigma("i", var("i"), var("n"), mul(var("i"), var("x")));
+        assert_eq!(expr.free_vars(), BTreeSet::from(["i", "n", "x"]));
+    }
+
+    #[test]
+    fn test_sigma_product() {
+        let env = Env::from_iter([("n", 5)]);
+        let i = || var("i");
+        // Sigma(1, 5, i) is the same as Summation([1, 2, 3, 4, 5])
+        assert_eq!(
+            eval(&sigma("i", Const(1), Const(5), i()), &env),
+            eval(&Summation((1..=5).map(Const).collect()), &env)
+        );
+        assert_eq!(
+            eval(&sigma("i", Const(1), var("n"), mul(i(), i())), &env),
+            Ok(55)
+        );
+        assert_eq!(eval(&product("i", Const(1), var("n"), i()), &env), Ok(120));
+        assert_eq!(eval(&product("i", Const(-2), Const(-1), i()), &env), Ok(2));
+
+        // Empty ranges
+        assert_eq!(eval(&sigma("i", Const(1), Const(0), i()), &env), Ok(0));
+        assert_eq!(eval(&product("i", Const(1), Const(0), i()), &env), Ok(1));
+        assert_eq!(
+            eval(
+                &sigma("i", var("n"), Const(1), div(Const(1), Const(0))),
+                &env
+            ),
+            Ok(0)
+        );
+
+        // The full range of `i64` can be used, without overflowing the loop variable
+        assert_eq!(
+            eval(
+                &sigma("i", Const(i64::MAX - 1), Const(i64::MAX), Const(1)),
+                &env
+            ),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn test_sigma_product_scoping() {
+        let env = Env::from_iter([("i", 100), ("n", 3)]);
+        let i = || var("i");
+        let j = || var("j");
+        // sigma(i, 1, n, sigma(j, i, n, i * j)) sums i * j for 1 <= i <= j <= 3
+        let expr = sigma(
+            "i",
+            Const(1),
+            var("n"),
+            sigma("j", i(), var("n"), mul(i(), j())),
+        );
+        assert_eq!(eval(&expr, &env), Ok(1 + 2 + 3 + 4 + 6 + 9));
+
+        // The inner loop shadows the outer one
+        let expr = sigma(
+            "i",
+            Const(1),
+            Const(2),
+            sigma("i", Const(10), Const(11), i()),
+        );
+        assert_eq!(eval(&expr, &env), Ok(42));
+
+        // The bounds are evaluated outside of the loop, and the loop variable
+        // is restored afterwards: sigma(i, i - 99, i - 97, i) + i
+        let expr = add(
+            sigma("i", sub(i(), Const(99)), sub(i(), Const(97)), i()),
+            i(),
+        );
+        assert_eq!(eval(&expr, &env), Ok(1 + 2 + 3 + 100));
+
+        // A `Let` in the body binds the loop variable only within itself
+        let expr = product(
+            "i",
+            Const(1),
+            Const(3),
+            add(let_in("i", Const(0), i()), i()),
+        );
+        assert_eq!(eval(&expr, &env), Ok(6));
+    }
+
+    #[test]
+    fn test_sigma_product_errors() {
+        let env = Env::from_iter([("n", 100)]);
+        let i = || var("i");
+        assert_eq!(
+            eval(&sigma("i", Const(1), Const(2), Const(i64::MAX)), &env),
+            Err(EvalError::Overflow)
+        );
+        // Like with a `Summation`, only the sum as a whole needs to fit
+        let expr = sigma("i", Const(-1), Const(1), mul(i(), Const(i64::MAX)));
+        assert_eq!(eval(&expr, &env), Ok(0));
+        let expr = sigma("i", Const(-2), Const(2), mul(i(), Const(i64::MAX / 2)));
+        assert_eq!(eval(&expr, &env), Ok(0));
+        // 100! doesn't fit in an `i64`, but 20! does
+        assert_eq!(
+            eval(&product("i", Const(1), var("n"), i()), &env),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            eval(&product("i", Const(1), Const(20), i()), &env),
+            Ok(2_432_902_008_176_640_000)
+        );
+        assert_eq!(
+            eval(&sigma("i", Const(-1), Const(1), div(Const(1), i())), &env),
+            Err(EvalError::DivisionByZero)
+        );
+        assert_eq!(
+            eval(&sigma("i", Const(1), var("m"), i()), &env),
+            Err(EvalError::UnboundVariable("m".to_string()))
+        );
+        // The loop variable is not bound outside of the loop
+        assert_eq!(
+            eval(&add(sigma("i", Const(1), Const(2), i()), i()), &env),
+            Err(EvalError::UnboundVariable("i".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        // 1 / 3 rounds towards zero with `i64`s, is approximated with `f64`s, and is exact with `Rational`s
+        let third = div(Const(1), Const(3));
+        assert_eq!(eval(&third, &Env::new()), Ok(0));
+        assert_eq!(eval(&third.cast::<f64>(), &Env::new()), Ok(1.0 / 3.0));
+        assert_eq!(
+            eval(&third.cast::<Rational>(), &Env::new()),
+            Ok(Rational::new(1, 3))
+        );
+
+        let expr = Summation(vec![third.clone(), third.clone(), third.clone()]);
+        assert_eq!(eval(&expr, &Env::new()), Ok(0));
+        assert_eq!(
+            eval(&expr.cast::<Rational>(), &Env::new()),
+            Ok(Rational::from(1))
+        );
+        // 0.1 + 0.2 is famously not 0.3 with `f64`s
+        let expr = add(div(Const(1), Const(10)), div(Const(2), Const(10)));
+        assert_ne!(eval(&expr.cast::<f64>(), &Env::new()), Ok(0.3));
+        assert_eq!(
+            eval(&expr.cast::<Rational>(), &Env::new()),
+            Ok(Rational::new(3, 10))
+        );
+
+        // Division by zero only fails with `i64`s and `Rational`s
+        let expr = div(Const(1), Const(0));
+        assert_eq!(eval(&expr, &Env::new()), Err(EvalError::DivisionByZero));
+        assert_eq!(
+            eval(&expr.cast::<Rational>(), &Env::new()),
+            Err(EvalError::DivisionByZero)
+        );
+        assert_eq!(eval(&expr.cast::<f64>(), &Env::new()), Ok(f64::INFINITY));
+
+        // The values of the variables have the same type as the constants
+        let env = Env::from_iter([("x", Rational::new(1, 2))]);
+        let expr = mul(var("x"), Const(Rational::from(3)));
+        assert_eq!(eval(&expr, &env), Ok(Rational::new(3, 2)));
+    }
+
+    #[test]
+    fn test_sigma_numbers() {
+        // sigma(i, 1, n, 1 / i) is the n-th harmonic number
+        let expr = sigma("i", Const(1), var("n"), div(Const(1), var("i")));
+        assert_eq!(eval(&expr, &Env::from_iter([("n", 4)])), Ok(1));
+        assert_eq!(
+            eval(&expr.cast(), &Env::from_iter([("n", Rational::from(4))])),
+            Ok(Rational::new(25, 12))
+        );
+        let harmonic = eval(&expr.cast(), &Env::from_iter([("n", 4.0)])).unwrap();
+        assert!((harmonic - 25.0 / 12.0).abs() < 1e-12);
+
+        // The bounds of the range have to be whole numbers
+        assert_eq!(
+            eval(&expr.cast(), &Env::from_iter([("n", Rational::new(9, 2))])),
+            Err(EvalError::NonIntegerBound)
+        );
+        assert_eq!(
+            eval(&expr.cast(), &Env::from_iter([("n", 4.5)])),
+            Err(EvalError::NonIntegerBound)
+        );
+        assert_eq!(
+            eval(&expr.cast(), &Env::from_iter([("n", f64::NAN)])),
+            Err(EvalError::NonIntegerBound)
+        );
+    }
+
+    #[test]
+    fn test_sigma_overflows_like_summation() {
+        // The `i`-th of three values, for `i` in `0..=2`, which only multiplies the values by 0 or 1
+        let pick = |values: [i64; 3]| {
+            let i = || var("i");
+            let half = |e| div(e, Const(2));
+            let first = half(mul(sub(Const(1), i()), sub(Const(2), i())));
+            let second = mul(i(), sub(Const(2), i()));
+            let third = half(mul(i(), sub(i(), Const(1))));
+            add(
+                add(mul(first, Const(values[0])), mul(second, Const(values[1]))),
+                mul(third, Const(values[2])),
+            )
+        };
+        let max = i64::MAX;
+        for (values, i64_sum, rational_sum) in [
+            ([1, 2, 3], Ok(6), Ok(6)),
+            ([-max, 0, max], Ok(0), Ok(0)),
+            (
+                [max, 1, 1],
+                Err(EvalError::Overflow),
+                Err(EvalError::Overflow),
+            ),
+            // An `i64` sum only needs to fit as a whole, but `Rational`s are added one by one
+            ([max, 1, -1], Ok(max), Err(EvalError::Overflow)),
+            ([max, max, -max], Ok(max), Err(EvalError::Overflow)),
+        ] {
+            let sigma = sigma("i", Const(0), Const(2), pick(values));
+            let summation = Summation(values.map(Const).to_vec());
+            assert_eq!(eval(&sigma, &Env::new()), i64_sum);
+            assert_eq!(eval(&summation, &Env::new()), i64_sum);
+            let rational_sum = rational_sum.map(Rational::from);
+            assert_eq!(eval(&sigma.cast(), &Env::new()), rational_sum);
+            assert_eq!(eval(&summation.cast(), &Env::new()), rational_sum);
+        }
+    }
+
+    #[test]
+    fn test_too_many_iterations() {
+        let n = MAX_ITERATIONS as i64;
+        // A range that never overflows still has a limit, which fails before the range is run
+        let expr = sigma("i", Const(0), Const(1_000_000_000_000_000_000), Const(0));
+        assert_eq!(eval(&expr, &Env::new()), Err(EvalError::TooManyIterations));
+        let expr = product("i", Const(i64::MIN), Const(i64::MAX), Const(1));
+        assert_eq!(eval(&expr, &Env::new()), Err(EvalError::TooManyIterations));
+
+        // The limit is on the iterations of all ranges together, including nested ones: here the two
+        // iterations of `i` and the first range of `j` fit, but the second range of `j` doesn't
+        let inner = sigma("j", Const(1), var("m"), Const(0));
+        let expr = sigma("i", Const(1), Const(2), inner);
+        assert_eq!(eval(&expr, &Env::from_iter([("m", 1000)])), Ok(0));
+        assert_eq!(
+            eval(&expr, &Env::from_iter([("m", n - 2)])),
+            Err(EvalError::TooManyIterations)
+        );
+        // An empty range doesn't count
+        let expr = sigma("i", Const(1), Const(0), Const(1));
+        assert_eq!(eval(&add(expr.clone(), expr), &Env::new()), Ok(0));
+    }
+}
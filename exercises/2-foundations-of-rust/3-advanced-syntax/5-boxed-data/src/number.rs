@@ -0,0 +1,293 @@
+//! The numbers an [`Expr`](crate::Expr) can be evaluated with: checked `i64`s, `f64`s,
+//! and exact [`Rational`]s.
+
+use std::{cmp::Ordering, fmt};
+
+use crate::EvalError;
+
+/// A number type that [`eval`](crate::eval) can evaluate an [`Expr`](crate::Expr) with.
+/// The arithmetic operations return an error instead of a number if the result can't be represented.
+pub trait Number: Copy + PartialEq + fmt::Debug + fmt::Display {
+    fn from_i64(k: i64) -> Self;
+
+    /// Returns the number as an `i64` if it is a whole number that fits in one,
+    /// for the bounds of a `Sigma` or `Product`
+    fn to_i64(self) -> Option<i64>;
+
+    fn try_add(self, rhs: Self) -> Result<Self, EvalError>;
+
+    fn try_sub(self, rhs: Self) -> Result<Self, EvalError>;
+
+    fn try_mul(self, rhs: Self) -> Result<Self, EvalError>;
+
+    fn try_div(self, rhs: Self) -> Result<Self, EvalError>;
+
+    /// Adds up the values of a `Summation` or `Sigma`, stopping at the first error.
+    /// By default, the values are added one by one.
+    fn try_sum(values: impl Iterator<Item = Result<Self, EvalError>>) -> Result<Self, EvalError> {
+        let mut acc = Self::from_i64(0);
+        for value in values {
+            acc = acc.try_add(value?)?;
+        }
+        Ok(acc)
+    }
+}
+
+/// Checked arithmetic, where division rounds towards zero
+impl Number for i64 {
+    fn from_i64(k: i64) -> Self {
+        k
+    }
+
+    fn to_i64(self) -> Option<i64> {
+        Some(self)
+    }
+
+    fn try_add(self, rhs: Self) -> Result<Self, EvalError> {
+        self.checked_add(rhs).ok_or(EvalError::Overflow)
+    }
+
+    fn try_sub(self, rhs: Self) -> Result<Self, EvalError> {
+        self.checked_sub(rhs).ok_or(EvalError::Overflow)
+    }
+
+    fn try_mul(self, rhs: Self) -> Result<Self, EvalError> {
+        self.checked_mul(rhs).ok_or(EvalError::Overflow)
+    }
+
+    fn try_div(self, rhs: Self) -> Result<Self, EvalError> {
+        if rhs == 0 {
+            return Err(EvalError::DivisionByZero);
+        }
+        // The only other way division can fail is `i64::MIN / -1`
+        self.checked_div(rhs).ok_or(EvalError::Overflow)
+    }
+
+    fn try_sum(values: impl Iterator<Item = Result<Self, EvalError>>) -> Result<Self, EvalError> {
+        // Only the sum as a whole needs to fit in an `i64`, not every partial sum.
+        // That way, the order in which the items are added doesn't matter. The `i128` itself
+        // can't overflow: that would take more than 2^64 values, many more than `MAX_ITERATIONS`.
+        let mut acc: i128 = 0;
+        for value in values {
+            acc += i128::from(value?);
+        }
+        i64::try_from(acc).map_err(|_| EvalError::Overflow)
+    }
+}
+
+/// Floating-point arithmetic, which never fails: like `f64` itself, it results in
+/// an infinity or NaN instead
+impl Number for f64 {
+    fn from_i64(k: i64) -> Self {
+        k as f64
+    }
+
+    fn to_i64(self) -> Option<i64> {
+        // `i64::MAX` can't be represented as an `f64`, but 2^63 can
+        let in_range = (i64::MIN as f64..-(i64::MIN as f64)).contains(&self);
+        (in_range && self.fract() == 0.0).then_some(self as i64)
+    }
+
+    fn try_add(self, rhs: Self) -> Result<Self, EvalError> {
+        Ok(self + rhs)
+    }
+
+    fn try_sub(self, rhs: Self) -> Result<Self, EvalError> {
+        Ok(self - rhs)
+    }
+
+    fn try_mul(self, rhs: Self) -> Result<Self, EvalError> {
+        Ok(self * rhs)
+    }
+
+    fn try_div(self, rhs: Self) -> Result<Self, EvalError> {
+        Ok(self / rhs)
+    }
+}
+
+/// An exact fraction of two `i64`s, which is always kept in lowest terms with a positive
+/// denominator, so that equal fractions compare equal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numer: i64,
+    denom: i64,
+}
+
+impl Rational {
+    /// Creates the fraction `numer / denom`
+    ///
+    /// # Panics
+    /// Panics if `denom` is zero, or if the fraction in lowest terms doesn't fit, as in `i64::MIN / -1`
+    pub fn new(numer: i64, denom: i64) -> Self {
+        assert!(denom != 0, "the denominator of a `Rational` can't be zero");
+        Self::reduce(numer.into(), denom.into()).expect("the fraction doesn't fit in a `Rational`")
+    }
+
+    /// The numerator, which has the sign of the fraction
+    pub fn numer(self) -> i64 {
+        self.numer
+    }
+
+    /// The denominator, which is always positive
+    pub fn denom(self) -> i64 {
+        self.denom
+    }
+
+    /// Reduces `numer / denom` to lowest terms, if that fits. The products of two `i64`s,
+    /// and the sums of two of those, fit in an `i128`, so the operations compute in `i128`s.
+    fn reduce(numer: i128, denom: i128) -> Option<Self> {
+        debug_assert!(denom != 0);
+        let gcd = gcd(numer.unsigned_abs(), denom.unsigned_abs()) as i128;
+        let (numer, denom) = if denom < 0 {
+            (-numer / gcd, -denom / gcd)
+        } else {
+            (numer / gcd, denom / gcd)
+        };
+        Some(Rational {
+            numer: numer.try_into().ok()?,
+            denom: denom.try_into().ok()?,
+        })
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl From<i64> for Rational {
+    fn from(k: i64) -> Self {
+        Rational { numer: k, denom: 1 }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // The denominators are positive, so multiplying both sides by them keeps the order
+        let lhs = i128::from(self.numer) * i128::from(other.denom);
+        let rhs = i128::from(other.numer) * i128::from(self.denom);
+        lhs.cmp(&rhs)
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denom == 1 {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+/// Exact arithmetic, which fails if the numerator or denominator of a result doesn't fit in an `i64`
+impl Number for Rational {
+    fn from_i64(k: i64) -> Self {
+        k.into()
+    }
+
+    fn to_i64(self) -> Option<i64> {
+        (self.denom == 1).then_some(self.numer)
+    }
+
+    fn try_add(self, rhs: Self) -> Result<Self, EvalError> {
+        let (a, b, c, d) = self.widen(rhs);
+        Self::reduce(a * d + c * b, b * d).ok_or(EvalError::Overflow)
+    }
+
+    fn try_sub(self, rhs: Self) -> Result<Self, EvalError> {
+        let (a, b, c, d) = self.widen(rhs);
+        Self::reduce(a * d - c * b, b * d).ok_or(EvalError::Overflow)
+    }
+
+    fn try_mul(self, rhs: Self) -> Result<Self, EvalError> {
+        let (a, b, c, d) = self.widen(rhs);
+        Self::reduce(a * c, b * d).ok_or(EvalError::Overflow)
+    }
+
+    fn try_div(self, rhs: Self) -> Result<Self, EvalError> {
+        if rhs.numer == 0 {
+            return Err(EvalError::DivisionByZero);
+        }
+        let (a, b, c, d) = self.widen(rhs);
+        Self::reduce(a * d, b * c).ok_or(EvalError::Overflow)
+    }
+}
+
+impl Rational {
+    /// Returns the numerators and denominators of `self` and `rhs` as `i128`s
+    fn widen(self, rhs: Self) -> (i128, i128, i128, i128) {
+        (
+            self.numer.into(),
+            self.denom.into(),
+            rhs.numer.into(),
+            rhs.denom.into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EvalError, Number, Rational};
+
+    #[test]
+    fn test_rational() {
+        assert_eq!(Rational::new(2, 4), Rational::new(1, 2));
+        assert_eq!(Rational::new(3, -6), Rational::new(-1, 2));
+        assert_eq!(Rational::new(-3, -6).numer(), 1);
+        assert_eq!(Rational::new(0, -6), Rational::from(0));
+        assert_eq!(Rational::new(i64::MIN, i64::MIN), Rational::from(1));
+        assert!(Rational::new(1, 3) < Rational::new(1, 2));
+        assert!(Rational::new(-1, 2) < Rational::new(-1, 3));
+        assert_eq!(Rational::new(-4, 6).to_string(), "-2/3");
+        assert_eq!(Rational::new(4, 2).to_string(), "2");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_rational_zero_denominator() {
+        Rational::new(1, 0);
+    }
+
+    #[test]
+    fn test_rational_arithmetic() {
+        let r = Rational::new;
+        assert_eq!(r(1, 6).try_add(r(1, 3)), Ok(r(1, 2)));
+        assert_eq!(r(1, 6).try_sub(r(1, 3)), Ok(r(-1, 6)));
+        assert_eq!(r(2, 3).try_mul(r(3, 4)), Ok(r(1, 2)));
+        assert_eq!(r(2, 3).try_div(r(-4, 3)), Ok(r(-1, 2)));
+        assert_eq!(r(2, 3).try_div(r(0, 3)), Err(EvalError::DivisionByZero));
+        assert_eq!(r(1, 2).to_i64(), None);
+        assert_eq!(r(4, 2).to_i64(), Some(2));
+
+        // Intermediate results may not fit in an `i64`, as long as the result in lowest terms does
+        let big = Rational::from(i64::MAX);
+        assert_eq!(big.try_mul(r(1, i64::MAX)), Ok(r(1, 1)));
+        assert_eq!(r(1, i64::MAX).try_add(r(1, i64::MAX)), Ok(r(2, i64::MAX)));
+        assert_eq!(big.try_add(r(1, 1)), Err(EvalError::Overflow));
+        assert_eq!(r(1, i64::MAX).try_mul(r(1, 2)), Err(EvalError::Overflow));
+        assert_eq!(
+            Rational::from(i64::MIN).try_div(Rational::from(-1)),
+            Err(EvalError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_f64_to_i64() {
+        assert_eq!(3.0.to_i64(), Some(3));
+        assert_eq!((-3.0).to_i64(), Some(-3));
+        assert_eq!(2.5.to_i64(), None);
+        assert_eq!((i64::MIN as f64).to_i64(), Some(i64::MIN));
+        assert_eq!((i64::MAX as f64).to_i64(), None);
+        assert_eq!(f64::NAN.to_i64(), None);
+        assert_eq!(f64::INFINITY.to_i64(), None);
+    }
+}
@@ -0,0 +1,517 @@
+//! A parser that turns text such as `"(x - 5) * 3 + sum(x, 1, 2)"` into an [`Expr`].
+//!
+//! The grammar, from lowest to highest precedence:
+//!
+//! ```text
+//! expr  := term (("+" | "-") term)*
+//! term  := unary (("*" | "/") unary)*
+//! unary := "-" unary | atom
+//! atom  := number | name | "sum" "(" (expr ("," expr)*)? ")" | "(" expr ")"
+//!        | "let" name "=" expr "in" expr
+//!        | ("sigma" | "product") "(" name "," expr "," expr "," expr ")"
+//! ```
+//!
+//! A name consists of letters, digits and underscores, and doesn't start with a digit.
+//! `let`, `in`, `sum`, `sigma` and `product` are keywords, which can't be used as names.
+//! `sigma(i, 1, n, i * i)` is the sum of `i * i` for `i` from 1 up to and including `n`.
+//! The body of a `let` extends as far to the right as possible, so `let x = 1 in x + 1`
+//! means `let x = 1 in (x + 1)`.
+//!
+//! All binary operators are left-associative, so `1 - 2 - 3` means `(1 - 2) - 3`.
+//! A minus sign in front of a number is part of the number, so `-5` is `Const(-5)`.
+//! In front of anything else, `-e` means `0 - e`.
+//!
+//! Expressions can be nested at most 256 levels deep, counting parentheses, minus signs and keywords,
+//! so that deeply nested input is an error instead of overflowing the stack.
+
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{add, div, let_in, mul, product, sigma, sub, var, Expr};
+
+/// The reason the input could not be parsed, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte offset in the input of the token that was not expected
+    pub offset: usize,
+    /// A description of what was expected at `offset`
+    pub expected: &'static str,
+    /// A description of what was found at `offset` instead
+    pub found: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {}, found {} at byte {}",
+            self.expected, self.found, self.offset
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parses `input` into an [`Expr`]
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        input,
+        pos: 0,
+        depth: 0,
+    };
+    let expr = parser.expr()?;
+    let token = parser.bump();
+    if token.kind != TokenKind::End {
+        return Err(token.error("an operator or end of input"));
+    }
+    Ok(expr)
+}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Number,
+    Ident,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LParen,
+    RParen,
+    Comma,
+    Equals,
+    Unknown,
+    End,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    offset: usize,
+}
+
+impl Token<'_> {
+    fn error(&self, expected: &'static str) -> ParseError {
+        let found = match self.kind {
+            TokenKind::End => "end of input".to_string(),
+            _ => format!("`{}`", self.text),
+        };
+        ParseError {
+            offset: self.offset,
+            expected,
+            found,
+        }
+    }
+}
+
+const KEYWORDS: [&str; 5] = ["let", "in", "sum", "sigma", "product"];
+
+/// How deeply [`Parser::unary`] may recurse
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    input: &'a str,
+    /// The byte offset up to which the input has been parsed
+    pos: usize,
+    /// The number of calls to [`Parser::unary`] that are in progress
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns the next token without consuming it
+    fn peek(&self) -> Token<'a> {
+        let rest = &self.input[self.pos..];
+        let offset = self.pos + (rest.len() - rest.trim_start().len());
+        let rest = &self.input[offset..];
+        let token = |kind, len| Token {
+            kind,
+            text: &rest[..len],
+            offset,
+        };
+
+        let Some(c) = rest.chars().next() else {
+            return token(TokenKind::End, 0);
+        };
+        let len_while = |f: fn(char) -> bool| rest.find(|c| !f(c)).unwrap_or(rest.len());
+        match c {
+            '0'..='9' => token(TokenKind::Number, len_while(|c| c.is_ascii_digit())),
+            c if c.is_alphabetic() || c == '_' => token(
+                TokenKind::Ident,
+                len_while(|c| c.is_alphanumeric() || c == '_'),
+            ),
+            '+' => token(TokenKind::Plus, 1),
+            '-' => token(TokenKind::Minus, 1),
+            '*' => token(TokenKind::Star, 1),
+            '/' => token(TokenKind::Slash, 1),
+            '(' => token(TokenKind::LParen, 1),
+            ')' => token(TokenKind::RParen, 1),
+            ',' => token(TokenKind::Comma, 1),
+            '=' => token(TokenKind::Equals, 1),
+            c => token(TokenKind::Unknown, c.len_utf8()),
+        }
+    }
+
+    /// Consumes the next token and returns it
+    fn bump(&mut self) -> Token<'a> {
+        let token = self.peek();
+        self.pos = token.offset + token.text.len();
+        token
+    }
+
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Plus => add,
+                TokenKind::Minus => sub,
+                _ => return Ok(lhs),
+            };
+            self.bump();
+            lhs = op(lhs, self.term()?);
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Star => mul,
+                TokenKind::Slash => div,
+                _ => return Ok(lhs),
+            };
+            self.bump();
+            lhs = op(lhs, self.unary()?);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        // Every level of nesting, with a minus sign, parentheses or a keyword, passes through here
+        if self.depth == MAX_DEPTH {
+            return Err(self
+                .peek()
+                .error("an expression that is less deeply nested"));
+        }
+        self.depth += 1;
+        let expr = self.minus_or_atom();
+        self.depth -= 1;
+        expr
+    }
+
+    fn minus_or_atom(&mut self) -> Result<Expr, ParseError> {
+        if self.peek().kind != TokenKind::Minus {
+            return self.atom();
+        }
+        let minus = self.bump();
+        let token = self.peek();
+        if token.kind == TokenKind::Number {
+            self.bump();
+            // Parse the sign along with the digits, so that `i64::MIN` can be written down
+            return format!("-{}", token.text)
+                .parse()
+                .map(Expr::Const)
+                .map_err(|_| minus.error("a number that fits in 64 bits"));
+        }
+        Ok(sub(Expr::Const(0), self.unary()?))
+    }
+
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        let token = self.bump();
+        match token.kind {
+            TokenKind::Number => token
+                .text
+                .parse()
+                .map(Expr::Const)
+                .map_err(|_| token.error("a number that fits in 64 bits")),
+            TokenKind::Ident => match token.text {
+                "sum" => self.sum(),
+                "let" => self.let_in(),
+                "sigma" => self.range(sigma),
+                "product" => self.range(product),
+                "in" => Err(token.error("an expression")),
+                name => Ok(var(name)),
+            },
+            TokenKind::LParen => {
+                let expr = self.expr()?;
+                let token = self.bump();
+                if token.kind != TokenKind::RParen {
+                    return Err(token.error("an operator or `)`"));
+                }
+                Ok(expr)
+            }
+            _ => Err(token.error("an expression")),
+        }
+    }
+
+    /// Parses `name = value in body`, after the `let` itself
+    fn let_in(&mut self) -> Result<Expr, ParseError> {
+        let name = self.bump();
+        if name.kind != TokenKind::Ident || KEYWORDS.contains(&name.text) {
+            return Err(name.error("a variable name"));
+        }
+        let token = self.bump();
+        if token.kind != TokenKind::Equals {
+            return Err(token.error("`=`"));
+        }
+        let value = self.expr()?;
+        let token = self.bump();
+        if token.kind != TokenKind::Ident || token.text != "in" {
+            return Err(token.error("an operator or `in`"));
+        }
+        let body = self.expr()?;
+        Ok(let_in(name.text, value, body))
+    }
+
+    /// Parses the arguments of `sigma` or `product`, after the keyword itself
+    fn range(&mut self, make: fn(&str, Expr, Expr, Expr) -> Expr) -> Result<Expr, ParseError> {
+        let token = self.bump();
+        if token.kind != TokenKind::LParen {
+            return Err(token.error("`(`"));
+        }
+        let name = self.bump();
+        if name.kind != TokenKind::Ident || KEYWORDS.contains(&name.text) {
+            return Err(name.error("a variable name"));
+        }
+        let token = self.bump();
+        if token.kind != TokenKind::Comma {
+            return Err(token.error("`,`"));
+        }
+        let mut args = Vec::with_capacity(3);
+        for delimiter in [TokenKind::Comma, TokenKind::Comma, TokenKind::RParen] {
+            args.push(self.expr()?);
+            let token = self.bump();
+            if token.kind != delimiter {
+                let expected = match delimiter {
+                    TokenKind::Comma => "an operator or `,`",
+                    _ => "an operator or `)`",
+                };
+                return Err(token.error(expected));
+            }
+        }
+        let [from, to, body] = args.try_into().unwrap();
+        Ok(make(name.text, from, to, body))
+    }
+
+    /// Parses the arguments of `sum`, after the `sum` itself
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let token = self.bump();
+        if token.kind != TokenKind::LParen {
+            return Err(token.error("`(`"));
+        }
+        let mut exprs = Vec::new();
+        if self.peek().kind == TokenKind::RParen {
+            self.bump();
+            return Ok(Expr::Summation(exprs));
+        }
+        loop {
+            exprs.push(self.expr()?);
+            let token = self.bump();
+            match token.kind {
+                TokenKind::Comma => continue,
+                TokenKind::RParen => return Ok(Expr::Summation(exprs)),
+                _ => return Err(token.error("an operator, `,` or `)`")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        add, div, let_in, mul, parse, product, sigma, sub, var, Const, Expr, ParseError, Summation,
+    };
+
+    fn error(offset: usize, expected: &'static str, found: &str) -> ParseError {
+        ParseError {
+            offset,
+            expected,
+            found: found.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_precedence_and_associativity() {
+        assert_eq!(
+            parse("(x - 5) * 3 + sum(x, 1, 2)"),
+            Ok(add(
+                mul(sub(var("x"), Const(5)), Const(3)),
+                Summation(vec![var("x"), Const(1), Const(2)])
+            ))
+        );
+        assert_eq!(
+            parse("1 + 2 * 3"),
+            Ok(add(Const(1), mul(Const(2), Const(3))))
+        );
+        assert_eq!(
+            parse("1 - 2 - 3"),
+            Ok(sub(sub(Const(1), Const(2)), Const(3)))
+        );
+        assert_eq!(
+            parse("8 / 4 / 2"),
+            Ok(div(div(Const(8), Const(4)), Const(2)))
+        );
+        assert_eq!(
+            parse("8 / (4 / 2)"),
+            Ok(div(Const(8), div(Const(4), Const(2))))
+        );
+        assert_eq!(parse(" ((x)) "), Ok(var("x")));
+        assert_eq!(parse("sum()"), Ok(Summation(vec![])));
+        assert_eq!("x*x".parse::<Expr>(), Ok(mul(var("x"), var("x"))));
+    }
+
+    #[test]
+    fn test_unary_minus() {
+        assert_eq!(parse("-5"), Ok(Const(-5)));
+        assert_eq!(parse("-x"), Ok(sub(Const(0), var("x"))));
+        assert_eq!(parse("x - -5"), Ok(sub(var("x"), Const(-5))));
+        assert_eq!(parse("-2 * 3"), Ok(mul(Const(-2), Const(3))));
+        assert_eq!(
+            parse("-(x + 1)"),
+            Ok(sub(Const(0), add(var("x"), Const(1))))
+        );
+        assert_eq!(parse("--x"), Ok(sub(Const(0), sub(Const(0), var("x")))));
+        assert_eq!(parse("-9223372036854775808"), Ok(Const(i64::MIN)));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse(""), Err(error(0, "an expression", "end of input")));
+        assert_eq!(parse("1 +"), Err(error(3, "an expression", "end of input")));
+        assert_eq!(
+            parse("(x - 5"),
+            Err(error(6, "an operator or `)`", "end of input"))
+        );
+        assert_eq!(
+            parse("x 5"),
+            Err(error(2, "an operator or end of input", "`5`"))
+        );
+        assert_eq!(parse("x + $"), Err(error(4, "an expression", "`$`")));
+        assert_eq!(
+            parse("let = 1 in 2"),
+            Err(error(4, "a variable name", "`=`"))
+        );
+        assert_eq!(
+            parse("let sum = 1 in 2"),
+            Err(error(4, "a variable name", "`sum`"))
+        );
+        assert_eq!(parse("let x 1 in 2"), Err(error(6, "`=`", "`1`")));
+        assert_eq!(
+            parse("let x = 1 2"),
+            Err(error(10, "an operator or `in`", "`2`"))
+        );
+        assert_eq!(parse("in"), Err(error(0, "an expression", "`in`")));
+        assert_eq!(parse("sum x"), Err(error(4, "`(`", "`x`")));
+        assert_eq!(
+            parse("sigma(1, 1, 2, 3)"),
+            Err(error(6, "a variable name", "`1`"))
+        );
+        assert_eq!(parse("sigma(i 1, 2, 3)"), Err(error(8, "`,`", "`1`")));
+        assert_eq!(
+            parse("product(i, 1, 2)"),
+            Err(error(15, "an operator or `,`", "`)`"))
+        );
+        assert_eq!(
+            parse("product(i, 1, 2, 3, 4)"),
+            Err(error(18, "an operator or `)`", "`,`"))
+        );
+        assert_eq!(
+            parse("sum(1; 2)"),
+            Err(error(5, "an operator, `,` or `)`", "`;`"))
+        );
+        assert_eq!(
+            parse("9223372036854775808"),
+            Err(error(
+                0,
+                "a number that fits in 64 bits",
+                "`9223372036854775808`"
+            ))
+        );
+        assert_eq!(
+            parse("é + #").unwrap_err().to_string(),
+            "expected an expression, found `#` at byte 5"
+        );
+    }
+
+    #[test]
+    fn test_depth() {
+        let nested =
+            |open: &str, n: usize, close: &str| format!("{}x{}", open.repeat(n), close.repeat(n));
+        assert!(parse(&nested("(", 255, ")")).is_ok());
+        assert!(parse(&nested("-", 255, "")).is_ok());
+        assert!(parse(&nested("sum(", 255, ")")).is_ok());
+        let too_deep = "an expression that is less deeply nested";
+        assert_eq!(
+            parse(&nested("(", 256, ")")),
+            Err(error(256, too_deep, "`x`"))
+        );
+        assert_eq!(
+            parse(&nested("-", 100_000, "")),
+            Err(error(256, too_deep, "`-`"))
+        );
+        assert_eq!(
+            parse(&nested("let x = ", 300, " in x")),
+            Err(error(256 * 8, too_deep, "`let`"))
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(
+            parse("width * height_2 + é"),
+            Ok(add(mul(var("width"), var("height_2")), var("é")))
+        );
+        assert_eq!(
+            parse("let y = x + 1 in y * y"),
+            Ok(let_in(
+                "y",
+                add(var("x"), Const(1)),
+                mul(var("y"), var("y"))
+            ))
+        );
+        // The body of a `let` extends as far to the right as possible
+        assert_eq!(
+            parse("1 + let x = 2 in x * 3"),
+            Ok(add(
+                Const(1),
+                let_in("x", Const(2), mul(var("x"), Const(3)))
+            ))
+        );
+        assert_eq!(
+            parse("(let x = 2 in x) * 3"),
+            Ok(mul(let_in("x", Const(2), var("x")), Const(3)))
+        );
+        assert_eq!(
+            parse("let x = let y = 1 in y in x"),
+            Ok(let_in("x", let_in("y", Const(1), var("y")), var("x")))
+        );
+    }
+
+    #[test]
+    fn test_sigma_product() {
+        assert_eq!(
+            parse("sigma(i, 1, n, i * i)"),
+            Ok(sigma("i", Const(1), var("n"), mul(var("i"), var("i"))))
+        );
+        assert_eq!(
+            parse("2 * product(k, -n, sigma(i, 0, 1, i), k + 1) - 1"),
+            Ok(sub(
+                mul(
+                    Const(2),
+                    product(
+                        "k",
+                        sub(Const(0), var("n")),
+                        sigma("i", Const(0), Const(1), var("i")),
+                        add(var("k"), Const(1))
+                    )
+                ),
+                Const(1)
+            ))
+        );
+    }
+}
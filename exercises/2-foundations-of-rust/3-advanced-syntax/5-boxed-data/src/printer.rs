@@ -0,0 +1,256 @@
+//! Prints an [`Expr`] in the syntax that [`parse`](crate::parse) reads, with only the parentheses
+//! that are needed to read it back as the same tree. Like the parser, it only handles `Expr<i64>`,
+//! as other constants, such as the fraction `1/3`, would not read back as a single constant.
+//!
+//! Two forms print differently than they are built: `0 - e` prints as `-e`, like the parser reads it,
+//! and a `Let` that is followed by an operator is put in parentheses, because its body would otherwise
+//! extend over that operator.
+
+use std::fmt;
+
+use crate::Expr;
+
+/// The precedence levels of the grammar, from lowest to highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    /// `+` and `-`
+    Sum,
+    /// `*` and `/`
+    Product,
+    /// Unary minus, and everything that can't be split up by an operator
+    Unary,
+}
+
+impl fmt::Display for Expr {
+    /// Prints the expression in infix notation, such that parsing the text gives back the same tree,
+    /// as long as all variable names are valid names for the parser
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_expr(f, self, Prec::Sum, true)
+    }
+}
+
+/// Writes `expr` where the grammar expects an expression of at least precedence `prec`.
+/// `tail` indicates that nothing but a delimiter such as `)`, `,` or `in` follows it.
+fn write_expr(f: &mut fmt::Formatter<'_>, expr: &Expr, prec: Prec, tail: bool) -> fmt::Result {
+    use Expr::*;
+    let parens = match expr {
+        // The body of a `Let` extends as far to the right as possible
+        Let(..) => !tail,
+        _ => precedence(expr) < prec,
+    };
+    if parens {
+        write!(f, "(")?;
+    }
+    let tail = tail || parens;
+    match expr {
+        Const(k) => write!(f, "{k}")?,
+        Var(name) => write!(f, "{name}")?,
+        Sub(lhs, rhs) if is_negation(lhs, rhs) => {
+            write!(f, "-")?;
+            write_expr(f, rhs, Prec::Unary, tail)?;
+        }
+        Add(lhs, rhs) => write_binary(f, lhs, "+", rhs, Prec::Sum, tail)?,
+        Sub(lhs, rhs) => write_binary(f, lhs, "-", rhs, Prec::Sum, tail)?,
+        Mul(lhs, rhs) => write_binary(f, lhs, "*", rhs, Prec::Product, tail)?,
+        Div(lhs, rhs) => write_binary(f, lhs, "/", rhs, Prec::Product, tail)?,
+        Summation(exprs) => {
+            write!(f, "sum(")?;
+            for (i, e) in exprs.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write_expr(f, e, Prec::Sum, true)?;
+            }
+            write!(f, ")")?;
+        }
+        Let(name, value, body) => {
+            write!(f, "let {name} = ")?;
+            write_expr(f, value, Prec::Sum, true)?;
+            write!(f, " in ")?;
+            write_expr(f, body, Prec::Sum, tail)?;
+        }
+        Sigma(name, from, to, body) | Product(name, from, to, body) => {
+            let keyword = if matches!(expr, Sigma(..)) {
+                "sigma"
+            } else {
+                "product"
+            };
+            write!(f, "{keyword}({name}, ")?;
+            write_expr(f, from, Prec::Sum, true)?;
+            write!(f, ", ")?;
+            write_expr(f, to, Prec::Sum, true)?;
+            write!(f, ", ")?;
+            write_expr(f, body, Prec::Sum, true)?;
+            write!(f, ")")?;
+        }
+    }
+    if parens {
+        write!(f, ")")?;
+    }
+    Ok(())
+}
+
+/// Writes a left-associative binary operator of precedence `prec`
+fn write_binary(
+    f: &mut fmt::Formatter<'_>,
+    lhs: &Expr,
+    op: &str,
+    rhs: &Expr,
+    prec: Prec,
+    tail: bool,
+) -> fmt::Result {
+    write_expr(f, lhs, prec, false)?;
+    write!(f, " {op} ")?;
+    // The right operand needs parentheses at the same precedence, as in `1 - (2 - 3)`
+    let rhs_prec = match prec {
+        Prec::Sum => Prec::Product,
+        _ => Prec::Unary,
+    };
+    write_expr(f, rhs, rhs_prec, tail)
+}
+
+fn precedence(expr: &Expr) -> Prec {
+    use Expr::*;
+    match expr {
+        Sub(lhs, rhs) if is_negation(lhs, rhs) => Prec::Unary,
+        Add(..) | Sub(..) => Prec::Sum,
+        Mul(..) | Div(..) => Prec::Product,
+        Const(_) | Var(_) | Summation(_) | Let(..) | Sigma(..) | Product(..) => Prec::Unary,
+    }
+}
+
+/// Returns whether `lhs - rhs` prints as `-rhs`, which is how the parser reads `-rhs`.
+/// That doesn't work for a non-negative constant, because `-5` is read as `Const(-5)`.
+fn is_negation(lhs: &Expr, rhs: &Expr) -> bool {
+    *lhs == Expr::Const(0) && !matches!(rhs, Expr::Const(k) if *k >= 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        add, div, let_in, mul, parse, product, sigma, sub, testing::random_expr, var, Const,
+        Summation,
+    };
+
+    #[test]
+    fn test_display() {
+        let x = || var("x");
+        let y = || var("y");
+        assert_eq!(add(sub(x(), Const(5)), Const(5)).to_string(), "x - 5 + 5");
+        assert_eq!(sub(x(), add(Const(5), Const(5))).to_string(), "x - (5 + 5)");
+        assert_eq!(mul(add(x(), y()), Const(3)).to_string(), "(x + y) * 3");
+        assert_eq!(add(x(), mul(y(), Const(3))).to_string(), "x + y * 3");
+        assert_eq!(div(x(), mul(y(), Const(3))).to_string(), "x / (y * 3)");
+        assert_eq!(mul(div(x(), y()), Const(3)).to_string(), "x / y * 3");
+        assert_eq!(
+            Summation(vec![x(), add(y(), Const(1))]).to_string(),
+            "sum(x, y + 1)"
+        );
+        assert_eq!(Summation(vec![]).to_string(), "sum()");
+        assert_eq!(
+            sigma("i", Const(1), var("n"), mul(var("i"), x())).to_string(),
+            "sigma(i, 1, n, i * x)"
+        );
+        assert_eq!(
+            mul(product("i", Const(1), Const(3), var("i")), x()).to_string(),
+            "product(i, 1, 3, i) * x"
+        );
+    }
+
+    #[test]
+    fn test_display_negation() {
+        let x = || var("x");
+        assert_eq!(Const(-5).to_string(), "-5");
+        assert_eq!(sub(x(), Const(-5)).to_string(), "x - -5");
+        assert_eq!(sub(Const(0), x()).to_string(), "-x");
+        assert_eq!(mul(sub(Const(0), x()), x()).to_string(), "-x * x");
+        assert_eq!(sub(Const(0), mul(x(), x())).to_string(), "-(x * x)");
+        assert_eq!(sub(Const(0), sub(Const(0), x())).to_string(), "--x");
+        assert_eq!(sub(Const(0), Const(-5)).to_string(), "--5");
+        // `-5` would be read as `Const(-5)`
+        assert_eq!(sub(Const(0), Const(5)).to_string(), "0 - 5");
+        assert_eq!(mul(sub(Const(0), Const(5)), x()).to_string(), "(0 - 5) * x");
+        assert_eq!(Const(i64::MIN).to_string(), "-9223372036854775808");
+    }
+
+    #[test]
+    fn test_display_let() {
+        let x = || var("x");
+        let expr = let_in("x", Const(2), mul(x(), Const(3)));
+        assert_eq!(expr.to_string(), "let x = 2 in x * 3");
+        // A `Let` at the end doesn't need parentheses
+        assert_eq!(
+            add(Const(1), expr.clone()).to_string(),
+            "1 + let x = 2 in x * 3"
+        );
+        assert_eq!(
+            mul(Const(1), expr.clone()).to_string(),
+            "1 * let x = 2 in x * 3"
+        );
+        assert_eq!(
+            sub(Const(0), expr.clone()).to_string(),
+            "-let x = 2 in x * 3"
+        );
+        assert_eq!(
+            let_in("y", expr.clone(), expr.clone()).to_string(),
+            "let y = let x = 2 in x * 3 in let x = 2 in x * 3"
+        );
+        assert_eq!(
+            Summation(vec![expr.clone(), x()]).to_string(),
+            "sum(let x = 2 in x * 3, x)"
+        );
+        // But one that is followed by an operator does
+        assert_eq!(
+            add(expr.clone(), Const(1)).to_string(),
+            "(let x = 2 in x * 3) + 1"
+        );
+        assert_eq!(
+            add(add(Const(1), expr.clone()), Const(1)).to_string(),
+            "1 + (let x = 2 in x * 3) + 1"
+        );
+        assert_eq!(
+            add(mul(Const(1), sub(Const(0), expr.clone())), Const(1)).to_string(),
+            "1 * -(let x = 2 in x * 3) + 1"
+        );
+        assert_eq!(
+            mul(add(Const(1), expr.clone()), Const(1)).to_string(),
+            "(1 + let x = 2 in x * 3) * 1"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0x9a7e);
+        for _ in 0..10_000 {
+            let expr = random_expr(&mut rng, 5);
+            let text = expr.to_string();
+            assert_eq!(parse(&text), Ok(expr.clone()), "printed as {text}");
+
+            // Each pair of parentheses is needed: without it, the text means something else,
+            // or can't be parsed at all
+            let mut open = Vec::new();
+            for (i, c) in text.char_indices() {
+                match c {
+                    '(' => open.push(i),
+                    ')' => {
+                        let start = open.pop().unwrap();
+                        let without = format!(
+                            "{}{}{}",
+                            &text[..start],
+                            &text[start + 1..i],
+                            &text[i + 1..]
+                        );
+                        assert_ne!(
+                            parse(&without),
+                            Ok(expr.clone()),
+                            "the parentheses at {start} in {text} are not needed"
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! An algebraic simplifier for [`Expr`], which cleans up trees that are generated programmatically.
+//! It folds constants with checked `i64` arithmetic, so it only handles `Expr<i64>`.
+
+use crate::{add, div, mul, sub, Expr};
+
+/// Returns a simplified version of `expr`, which evaluates to the same value. It
+///
+/// - folds operations on constants, such as `2 * 3` into `6`,
+/// - applies the identities `x + 0 = 0 + x = x`, `x - 0 = x`, `x - x = 0`,
+///   `x * 1 = 1 * x = x`, `x * 0 = 0 * x = 0` and `x / 1 = x`,
+/// - flattens nested `Summation`s into a single one, adding up its constant items,
+/// - removes a `Let` of which the variable is not used in the body,
+/// - replaces a `Sigma` or `Product` over an empty range, or of which the body is 0 or 1 respectively,
+///   by 0 or 1.
+///
+/// If `expr` evaluates to a value, the simplified expression evaluates to that same value.
+/// Simplifying never introduces an error, but it may remove one: in `(1 / 0) * 0`,
+/// the division by zero is simplified away along with the multiplication.
+/// Operations on constants that would fail, such as `1 / 0`, are left alone.
+pub fn simplify(expr: &Expr) -> Expr {
+    use Expr::*;
+    match expr {
+        Const(_) | Var(_) => expr.clone(),
+        Add(lhs, rhs) => match (simplify(lhs), simplify(rhs)) {
+            (Const(a), Const(b)) if a.checked_add(b).is_some() => Const(a + b),
+            (x, Const(0)) | (Const(0), x) => x,
+            (lhs, rhs) => add(lhs, rhs),
+        },
+        Sub(lhs, rhs) => match (simplify(lhs), simplify(rhs)) {
+            (Const(a), Const(b)) if a.checked_sub(b).is_some() => Const(a - b),
+            (x, Const(0)) => x,
+            (lhs, rhs) if lhs == rhs => Const(0),
+            (lhs, rhs) => sub(lhs, rhs),
+        },
+        Mul(lhs, rhs) => match (simplify(lhs), simplify(rhs)) {
+            (Const(a), Const(b)) if a.checked_mul(b).is_some() => Const(a * b),
+            (_, Const(0)) | (Const(0), _) => Const(0),
+            (x, Const(1)) | (Const(1), x) => x,
+            (lhs, rhs) => mul(lhs, rhs),
+        },
+        Div(lhs, rhs) => match (simplify(lhs), simplify(rhs)) {
+            (Const(a), Const(b)) if b != 0 && a.checked_div(b).is_some() => Const(a / b),
+            (x, Const(1)) => x,
+            (lhs, rhs) => div(lhs, rhs),
+        },
+        Summation(exprs) => simplify_summation(exprs),
+        Let(name, value, body) => {
+            let body = simplify(body);
+            if body.free_vars().contains(name.as_str()) {
+                Let(name.clone(), Box::new(simplify(value)), Box::new(body))
+            } else {
+                body
+            }
+        }
+        Sigma(name, from, to, body) => simplify_range(Sigma, name, from, to, body, 0),
+        Product(name, from, to, body) => simplify_range(Product, name, from, to, body, 1),
+    }
+}
+
+/// Simplifies a `Sigma` or `Product`, where `identity` is the value of an empty range
+fn simplify_range(
+    make: fn(String, Box<Expr>, Box<Expr>, Box<Expr>) -> Expr,
+    name: &str,
+    from: &Expr,
+    to: &Expr,
+    body: &Expr,
+    identity: i64,
+) -> Expr {
+    let (from, to, body) = (simplify(from), simplify(to), simplify(body));
+    match (&from, &to, &body) {
+        (Expr::Const(from), Expr::Const(to), _) if from > to => Expr::Const(identity),
+        (_, _, Expr::Const(k)) if *k == identity => Expr::Const(identity),
+        _ => make(
+            name.to_string(),
+            Box::new(from),
+            Box::new(to),
+            Box::new(body),
+        ),
+    }
+}
+
+fn simplify_summation(exprs: &[Expr]) -> Expr {
+    let mut items = Vec::new();
+    let mut constants = Vec::new();
+    for e in exprs {
+        match simplify(e) {
+            // A simplified `Summation` is already flat
+            Expr::Summation(nested) => items.extend(nested),
+            item => items.push(item),
+        }
+    }
+    items.retain(|item| match item {
+        Expr::Const(k) => {
+            constants.push(*k);
+            false
+        }
+        _ => true,
+    });
+
+    // Only the sum as a whole needs to fit in an `i64`, so the constants can be added up first
+    let constant: i128 = constants.iter().copied().map(i128::from).sum();
+    match i64::try_from(constant) {
+        Ok(0) => {}
+        Ok(constant) => items.push(Expr::Const(constant)),
+        Err(_) => items.extend(constants.into_iter().map(Expr::Const)),
+    }
+
+    match items.len() {
+        0 => Expr::Const(0),
+        1 => items.pop().unwrap(),
+        _ => Expr::Summation(items),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        add, div, eval, let_in, mul, product, sigma, simplify, sub,
+        testing::{random_env, random_expr},
+        var, Const, Expr, Summation,
+    };
+
+    #[test]
+    fn test_identities() {
+        let x = || var("x");
+        assert_eq!(simplify(&add(x(), Const(0))), x());
+        assert_eq!(simplify(&add(Const(0), x())), x());
+        assert_eq!(simplify(&sub(x(), Const(0))), x());
+        assert_eq!(simplify(&sub(x(), x())), Const(0));
+        assert_eq!(simplify(&mul(x(), Const(1))), x());
+        assert_eq!(simplify(&mul(Const(1), x())), x());
+        assert_eq!(simplify(&mul(x(), Const(0))), Const(0));
+        assert_eq!(simplify(&mul(Const(0), x())), Const(0));
+        assert_eq!(simplify(&div(x(), Const(1))), x());
+        // `0 - x` is how the parser writes `-x`
+        assert_eq!(simplify(&sub(Const(0), x())), sub(Const(0), x()));
+        assert_eq!(
+            simplify(&add(mul(sub(x(), x()), var("y")), mul(Const(1), var("z")))),
+            var("z")
+        );
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        assert_eq!(simplify(&mul(add(Const(1), Const(2)), Const(3))), Const(9));
+        assert_eq!(
+            simplify(&add(var("x"), mul(Const(2), Const(3)))),
+            add(var("x"), Const(6))
+        );
+        assert_eq!(simplify(&div(Const(7), Const(2))), Const(3));
+        // Operations that would fail are left for `eval` to report
+        assert_eq!(simplify(&div(Const(1), Const(0))), div(Const(1), Const(0)));
+        assert_eq!(
+            simplify(&add(Const(i64::MAX), Const(1))),
+            add(Const(i64::MAX), Const(1))
+        );
+    }
+
+    #[test]
+    fn test_summation() {
+        assert_eq!(
+            simplify(&Summation(vec![
+                Const(1),
+                Summation(vec![var("x"), Const(2), Summation(vec![var("y")])]),
+                Const(-3),
+                Summation(vec![]),
+            ])),
+            Summation(vec![var("x"), var("y")])
+        );
+        assert_eq!(
+            simplify(&Summation(vec![var("x"), Const(1), Const(2)])),
+            Summation(vec![var("x"), Const(3)])
+        );
+        assert_eq!(
+            simplify(&Summation(vec![add(var("x"), Const(0))])),
+            var("x")
+        );
+        assert_eq!(simplify(&Summation(vec![])), Const(0));
+        assert_eq!(
+            simplify(&Summation(vec![Const(i64::MAX), Const(1), Const(-1)])),
+            Const(i64::MAX)
+        );
+        assert_eq!(
+            simplify(&Summation(vec![Const(i64::MAX), Const(1)])),
+            Summation(vec![Const(i64::MAX), Const(1)])
+        );
+    }
+
+    #[test]
+    fn test_sigma_product() {
+        let i = || var("i");
+        assert_eq!(
+            simplify(&sigma("i", Const(1), var("n"), mul(i(), Const(1)))),
+            sigma("i", Const(1), var("n"), i())
+        );
+        assert_eq!(simplify(&sigma("i", Const(2), Const(1), i())), Const(0));
+        assert_eq!(
+            simplify(&product("i", Const(2), sub(Const(3), Const(2)), i())),
+            Const(1)
+        );
+        assert_eq!(
+            simplify(&sigma("i", Const(1), var("n"), sub(i(), i()))),
+            Const(0)
+        );
+        assert_eq!(
+            simplify(&product("i", Const(1), var("n"), div(i(), i()))),
+            product("i", Const(1), var("n"), div(i(), i()))
+        );
+        assert_eq!(
+            simplify(&product("i", Const(1), var("n"), Const(1))),
+            Const(1)
+        );
+    }
+
+    #[test]
+    fn test_let() {
+        assert_eq!(simplify(&let_in("x", var("y"), Const(1))), Const(1));
+        assert_eq!(
+            simplify(&let_in(
+                "x",
+                add(var("y"), Const(0)),
+                mul(var("x"), Const(1))
+            )),
+            let_in("x", var("y"), var("x"))
+        );
+        // The `x` in the body is removed by simplifying
+        assert_eq!(
+            simplify(&let_in("x", var("y"), mul(var("x"), Const(0)))),
+            Const(0)
+        );
+    }
+
+    fn size(expr: &Expr) -> usize {
+        use Expr::*;
+        match expr {
+            Const(_) | Var(_) => 1,
+            Add(lhs, rhs) | Sub(lhs, rhs) | Mul(lhs, rhs) | Div(lhs, rhs) | Let(_, lhs, rhs) => {
+                1 + size(lhs) + size(rhs)
+            }
+            Summation(exprs) => 1 + exprs.iter().map(size).sum::<usize>(),
+            Sigma(_, from, to, body) | Product(_, from, to, body) => {
+                1 + size(from) + size(to) + size(body)
+            }
+        }
+    }
+
+    #[test]
+    fn test_preserves_eval() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..10_000 {
+            let expr = random_expr(&mut rng, 5);
+            let simplified = simplify(&expr);
+            assert!(
+                size(&simplified) <= size(&expr),
+                "{expr:?} grew into {simplified:?}"
+            );
+            for _ in 0..5 {
+                let env = random_env(&mut rng);
+                if let Ok(value) = eval(&expr, &env) {
+                    assert_eq!(
+                        eval(&simplified, &env),
+                        Ok(value),
+                        "{expr:?} was simplified into {simplified:?}, with {env:?}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Random expressions and environments for the property tests.
+
+use rand::{rngs::StdRng, Rng};
+
+use crate::{add, div, let_in, mul, product, sigma, sub, var, Const, Env, Expr, Summation};
+
+/// The variables that random expressions use, and that random environments bind
+pub const NAMES: [&str; 3] = ["x", "y", "z"];
+
+/// The loop variables of the `Sigma`s and `Product`s in random expressions. They are only bound
+/// by loops, which keeps the ranges small: a bound is either a small constant or a loop variable.
+const LOOP_NAMES: [&str; 2] = ["i", "j"];
+
+/// Generates a random expression of at most `depth` levels deep, using small constants
+/// so that overflow, division by zero, and all of the identities show up every now and then
+pub fn random_expr(rng: &mut StdRng, depth: u32) -> Expr {
+    let choice = if depth == 0 {
+        rng.gen_range(0..2)
+    } else {
+        rng.gen_range(0..10)
+    };
+    let mut random = || random_expr(rng, depth - 1);
+    match choice {
+        0 => Const(rng.gen_range(-3..=3)),
+        1 => {
+            let names: &[&str] = if rng.gen_bool(0.8) {
+                &NAMES
+            } else {
+                &LOOP_NAMES
+            };
+            var(names[rng.gen_range(0..names.len())])
+        }
+        2 => add(random(), random()),
+        3 => sub(random(), random()),
+        4 => mul(random(), random()),
+        5 => div(random(), random()),
+        6 => {
+            let len = rng.gen_range(0..4);
+            Summation((0..len).map(|_| random_expr(rng, depth - 1)).collect())
+        }
+        7 => {
+            let name = NAMES[rng.gen_range(0..NAMES.len())];
+            let_in(
+                name,
+                random_expr(rng, depth - 1),
+                random_expr(rng, depth - 1),
+            )
+        }
+        _ => {
+            let name = LOOP_NAMES[rng.gen_range(0..LOOP_NAMES.len())];
+            let mut bound = || {
+                if rng.gen_bool(0.7) {
+                    Const(rng.gen_range(-2..=3))
+                } else {
+                    var(LOOP_NAMES[rng.gen_range(0..LOOP_NAMES.len())])
+                }
+            };
+            let (from, to) = (bound(), bound());
+            let body = random_expr(rng, depth - 1);
+            if choice == 8 {
+                sigma(name, from, to, body)
+            } else {
+                product(name, from, to, body)
+            }
+        }
+    }
+}
+
+/// Generates a random environment for the variables in [`NAMES`], which leaves
+/// a variable unbound every now and then
+pub fn random_env(rng: &mut StdRng) -> Env {
+    let mut env = Env::new();
+    for name in NAMES {
+        if rng.gen_bool(0.9) {
+            env.set(name, rng.gen_range(-5..=5));
+        }
+    }
+    env
+}
@@ -0,0 +1,516 @@
+//! A compiler from an [`Expr`] to a flat list of [`Instr`]uctions, and a stack machine to run them.
+//!
+//! Evaluating the same expression many times with [`eval`](crate::eval) walks the tree over and over,
+//! and looks up every variable by name. A [`Program`] resolves the variables to numbered slots once,
+//! and a [`Vm`] can be reused to run it, so that running it doesn't allocate.
+//! The `Vm` computes with `i64`s, so it only runs an `Expr<i64>`, with the same results as `eval`.
+
+use crate::{count_iterations, Env, EvalError, Expr};
+
+/// An instruction of the stack machine, which pops its operands from the stack,
+/// and pushes its result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instr {
+    /// Push a constant
+    Const(i64),
+    /// Push the value of the variable in a slot
+    Load(usize),
+    /// Pop a value, and store it in a slot
+    Store(usize),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// Pop the given number of values, and push their sum
+    Sum(usize),
+    /// Start a running sum for a `Sigma`, at 0
+    SumStart,
+    /// Pop a value, and add it to the running sum
+    SumAdd,
+    /// End the running sum, and push it. Like with `Sum`, only the sum as a whole needs to fit.
+    SumEnd,
+    /// Jump to `exit` if the loop variable in slot `var` is greater than the end of the range in slot `end`
+    LoopStart {
+        var: usize,
+        end: usize,
+        exit: usize,
+    },
+    /// Increment the loop variable in slot `var` and jump to `body`, unless it has reached
+    /// the end of the range in slot `end`
+    LoopNext {
+        var: usize,
+        end: usize,
+        body: usize,
+    },
+}
+
+/// An [`Expr`] compiled by [`compile`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    instrs: Vec<Instr>,
+    /// The names of the variables in each slot. The first `inputs` slots hold the free variables
+    /// of the expression, the others are bound by a `Let`, or are the variable and the end of
+    /// the range of a loop.
+    slots: Vec<String>,
+    inputs: usize,
+}
+
+impl Program {
+    pub fn instrs(&self) -> &[Instr] {
+        &self.instrs
+    }
+
+    /// Returns the names of the free variables of the expression, in alphabetical order,
+    /// which is the order in which [`Vm::run`] expects their values
+    pub fn inputs(&self) -> &[String] {
+        &self.slots[..self.inputs]
+    }
+
+    /// Runs the program, looking up the values of its inputs in `env`, just like [`eval`](crate::eval)
+    pub fn eval(&self, env: &Env) -> Result<i64, EvalError> {
+        Vm::new().run_env(self, env)
+    }
+}
+
+/// Compiles `expr` into a [`Program`], which evaluates to the same value or error as `expr`
+pub fn compile(expr: &Expr) -> Program {
+    let inputs: Vec<String> = expr.free_vars().into_iter().map(String::from).collect();
+    let mut compiler = Compiler {
+        instrs: Vec::new(),
+        inputs: inputs.len(),
+        slots: inputs,
+        scope: Vec::new(),
+    };
+    compiler.compile(expr);
+    Program {
+        instrs: compiler.instrs,
+        slots: compiler.slots,
+        inputs: compiler.inputs,
+    }
+}
+
+struct Compiler<'a> {
+    instrs: Vec<Instr>,
+    slots: Vec<String>,
+    inputs: usize,
+    /// The variables bound by the surrounding `Let`s, and their slots, innermost last
+    scope: Vec<(&'a str, usize)>,
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(&mut self, expr: &'a Expr) {
+        use Expr::*;
+        match expr {
+            Const(k) => self.instrs.push(Instr::Const(*k)),
+            Var(name) => {
+                let slot = self.slot_of(name);
+                self.instrs.push(Instr::Load(slot));
+            }
+            Add(lhs, rhs) => self.binary(lhs, rhs, Instr::Add),
+            Sub(lhs, rhs) => self.binary(lhs, rhs, Instr::Sub),
+            Mul(lhs, rhs) => self.binary(lhs, rhs, Instr::Mul),
+            Div(lhs, rhs) => self.binary(lhs, rhs, Instr::Div),
+            Summation(exprs) => {
+                for e in exprs {
+                    self.compile(e);
+                }
+                self.instrs.push(Instr::Sum(exprs.len()));
+            }
+            Let(name, value, body) => {
+                // The variable is only bound in the body, so compile the value first
+                self.compile(value);
+                let slot = self.new_slot(name);
+                self.instrs.push(Instr::Store(slot));
+                self.scope.push((name, slot));
+                self.compile(body);
+                self.scope.pop();
+            }
+            Sigma(name, from, to, body) => {
+                self.instrs.push(Instr::SumStart);
+                self.range(name, from, to, body, Instr::SumAdd);
+                self.instrs.push(Instr::SumEnd);
+            }
+            Product(name, from, to, body) => {
+                self.instrs.push(Instr::Const(1));
+                self.range(name, from, to, body, Instr::Mul);
+            }
+        }
+    }
+
+    /// Compiles a loop that combines the values of `body` with `op`, starting from the value
+    /// that is on top of the stack, or the running sum:
+    ///
+    /// ```text
+    ///       <from> Store(var) <to> Store(end)
+    ///       LoopStart { exit }
+    /// body: <body> op
+    ///       LoopNext { body }
+    /// exit:
+    /// ```
+    fn range(&mut self, name: &'a str, from: &'a Expr, to: &'a Expr, body: &'a Expr, op: Instr) {
+        // The loop variable is only bound in the body, so compile the bounds first
+        self.compile(from);
+        self.compile(to);
+        let (var, end) = (self.new_slot(name), self.new_slot(name));
+        self.instrs.push(Instr::Store(end));
+        self.instrs.push(Instr::Store(var));
+
+        let start = self.instrs.len();
+        // The exit is patched in once the body is compiled
+        self.instrs.push(Instr::LoopStart { var, end, exit: 0 });
+        let body_start = self.instrs.len();
+        self.scope.push((name, var));
+        self.compile(body);
+        self.scope.pop();
+        self.instrs.push(op);
+        self.instrs.push(Instr::LoopNext {
+            var,
+            end,
+            body: body_start,
+        });
+        let exit = self.instrs.len();
+        self.instrs[start] = Instr::LoopStart { var, end, exit };
+    }
+
+    fn new_slot(&mut self, name: &str) -> usize {
+        self.slots.push(name.to_string());
+        self.slots.len() - 1
+    }
+
+    fn binary(&mut self, lhs: &'a Expr, rhs: &'a Expr, instr: Instr) {
+        self.compile(lhs);
+        self.compile(rhs);
+        self.instrs.push(instr);
+    }
+
+    fn slot_of(&self, name: &str) -> usize {
+        if let Some(&(_, slot)) = self.scope.iter().rev().find(|(bound, _)| *bound == name) {
+            return slot;
+        }
+        self.slots[..self.inputs]
+            .iter()
+            .position(|input| input == name)
+            .expect("free variables have a slot")
+    }
+}
+
+/// A stack machine that runs a [`Program`]. It keeps its stack and slots between runs,
+/// so that running a program many times only allocates the first time.
+#[derive(Debug, Default)]
+pub struct Vm {
+    stack: Vec<i64>,
+    /// The values of the variables, where `None` is an unbound input
+    slots: Vec<Option<i64>>,
+    /// The running sums of the `Sigma`s, innermost last
+    sums: Vec<i128>,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `program` with the values of its [inputs](Program::inputs), in the same order.
+    ///
+    /// # Panics
+    /// Panics if the number of values is not the number of inputs
+    pub fn run(&mut self, program: &Program, inputs: &[i64]) -> Result<i64, EvalError> {
+        assert_eq!(
+            inputs.len(),
+            program.inputs,
+            "expected a value for each of {:?}",
+            program.inputs()
+        );
+        self.slots.clear();
+        self.slots.extend(inputs.iter().copied().map(Some));
+        self.execute(program)
+    }
+
+    /// Runs `program`, looking up the values of its inputs in `env`
+    pub fn run_env(&mut self, program: &Program, env: &Env) -> Result<i64, EvalError> {
+        self.slots.clear();
+        self.slots
+            .extend(program.inputs().iter().map(|name| env.get(name)));
+        self.execute(program)
+    }
+
+    fn execute(&mut self, program: &Program) -> Result<i64, EvalError> {
+        let overflow = |result: Option<i64>| result.ok_or(EvalError::Overflow);
+        self.slots.resize(program.slots.len(), None);
+        self.stack.clear();
+        self.sums.clear();
+        let mut iterations = 0;
+        let mut pc = 0;
+        while let Some(&instr) = program.instrs.get(pc) {
+            pc += 1;
+            let result = match instr {
+                Instr::Const(k) => k,
+                Instr::Load(slot) => self.slots[slot]
+                    .ok_or_else(|| EvalError::UnboundVariable(program.slots[slot].clone()))?,
+                Instr::Store(slot) => {
+                    self.slots[slot] = Some(self.pop());
+                    continue;
+                }
+                Instr::Add => {
+                    let (lhs, rhs) = self.pop_operands();
+                    overflow(lhs.checked_add(rhs))?
+                }
+                Instr::Sub => {
+                    let (lhs, rhs) = self.pop_operands();
+                    overflow(lhs.checked_sub(rhs))?
+                }
+                Instr::Mul => {
+                    let (lhs, rhs) = self.pop_operands();
+                    overflow(lhs.checked_mul(rhs))?
+                }
+                Instr::Div => {
+                    let (lhs, rhs) = self.pop_operands();
+                    if rhs == 0 {
+                        return Err(EvalError::DivisionByZero);
+                    }
+                    overflow(lhs.checked_div(rhs))?
+                }
+                Instr::Sum(len) => {
+                    // Like `eval`, only the sum as a whole needs to fit in an `i64`
+                    let items = self.stack.drain(self.stack.len() - len..);
+                    overflow(i64::try_from(items.map(i128::from).sum::<i128>()).ok())?
+                }
+                Instr::SumStart => {
+                    self.sums.push(0);
+                    continue;
+                }
+                Instr::SumAdd => {
+                    let value = self.pop();
+                    *self.sums.last_mut().expect("a running sum was started") += i128::from(value);
+                    continue;
+                }
+                Instr::SumEnd => {
+                    let sum = self.sums.pop().expect("a running sum was started");
+                    overflow(i64::try_from(sum).ok())?
+                }
+                Instr::LoopStart { var, end, exit } => {
+                    count_iterations(&mut iterations, self.loop_slot(var), self.loop_slot(end))?;
+                    if self.loop_slot(var) > self.loop_slot(end) {
+                        pc = exit;
+                    }
+                    continue;
+                }
+                Instr::LoopNext { var, end, body } => {
+                    // Checking before incrementing keeps a loop up to `i64::MAX` from overflowing
+                    let i = self.loop_slot(var);
+                    if i < self.loop_slot(end) {
+                        self.slots[var] = Some(i + 1);
+                        pc = body;
+                    }
+                    continue;
+                }
+            };
+            self.stack.push(result);
+        }
+        let result = self.pop();
+        debug_assert!(self.stack.is_empty());
+        Ok(result)
+    }
+
+    fn loop_slot(&self, slot: usize) -> i64 {
+        self.slots[slot].expect("the slots of a loop are stored before the loop starts")
+    }
+
+    fn pop(&mut self) -> i64 {
+        self.stack
+            .pop()
+            .expect("a compiled program never pops an empty stack")
+    }
+
+    fn pop_operands(&mut self) -> (i64, i64) {
+        let rhs = self.pop();
+        let lhs = self.pop();
+        (lhs, rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        add, compile, div, eval, let_in, mul, parse, product, sigma, sub,
+        testing::{random_env, random_expr},
+        var, Const, Env, EvalError, Instr, Summation, Vm,
+    };
+
+    #[test]
+    fn test_compile() {
+        let program = compile(&parse("(y - 5) * x + sum(1, y)").unwrap());
+        assert_eq!(program.inputs(), ["x", "y"]);
+        assert_eq!(
+            program.instrs(),
+            [
+                Instr::Load(1),
+                Instr::Const(5),
+                Instr::Sub,
+                Instr::Load(0),
+                Instr::Mul,
+                Instr::Const(1),
+                Instr::Load(1),
+                Instr::Sum(2),
+                Instr::Add,
+            ]
+        );
+
+        // Each `Let` gets a slot of its own, after the inputs
+        let program = compile(&parse("let x = x + 1 in (let x = x * 10 in x) + x").unwrap());
+        assert_eq!(program.inputs(), ["x"]);
+        assert_eq!(
+            program.instrs(),
+            [
+                Instr::Load(0),
+                Instr::Const(1),
+                Instr::Add,
+                Instr::Store(1),
+                Instr::Load(1),
+                Instr::Const(10),
+                Instr::Mul,
+                Instr::Store(2),
+                Instr::Load(2),
+                Instr::Load(1),
+                Instr::Add,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_loop() {
+        let program = compile(&parse("sigma(i, 1, n, i * i)").unwrap());
+        assert_eq!(program.inputs(), ["n"]);
+        assert_eq!(
+            program.instrs(),
+            [
+                Instr::SumStart,
+                Instr::Const(1),
+                Instr::Load(0),
+                Instr::Store(2),
+                Instr::Store(1),
+                Instr::LoopStart {
+                    var: 1,
+                    end: 2,
+                    exit: 11
+                },
+                Instr::Load(1),
+                Instr::Load(1),
+                Instr::Mul,
+                Instr::SumAdd,
+                Instr::LoopNext {
+                    var: 1,
+                    end: 2,
+                    body: 6
+                },
+                Instr::SumEnd,
+            ]
+        );
+        let mut vm = Vm::new();
+        assert_eq!(vm.run(&program, &[3]), Ok(1 + 4 + 9));
+        assert_eq!(vm.run(&program, &[0]), Ok(0));
+
+        let program = compile(&product("i", Const(1), var("n"), var("i")));
+        assert_eq!(vm.run(&program, &[5]), Ok(120));
+        assert_eq!(vm.run(&program, &[-5]), Ok(1));
+        assert_eq!(vm.run(&program, &[100]), Err(EvalError::Overflow));
+
+        let program = compile(&sigma("i", Const(i64::MAX - 1), Const(i64::MAX), Const(1)));
+        assert_eq!(vm.run(&program, &[]), Ok(2));
+        // Like with a `Summation`, only the sum as a whole needs to fit
+        let expr = sigma("i", Const(-1), Const(1), mul(var("i"), Const(i64::MAX)));
+        assert_eq!(vm.run(&compile(&expr), &[]), Ok(0));
+        assert_eq!(eval(&expr, &Env::new()), Ok(0));
+    }
+
+    #[test]
+    fn test_run() {
+        let expr = let_in("y", add(var("x"), Const(1)), mul(var("y"), var("y")));
+        let program = compile(&expr);
+        let mut vm = Vm::new();
+        for x in -10..10 {
+            assert_eq!(vm.run(&program, &[x]), Ok((x + 1) * (x + 1)));
+        }
+        let env = Env::from_iter([("x", 2)]);
+        assert_eq!(program.eval(&env), Ok(9));
+        assert_eq!(vm.run_env(&program, &env), Ok(9));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_run_missing_input() {
+        let _ = Vm::new().run(&compile(&add(var("x"), var("y"))), &[1]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let env = Env::from_iter([("x", i64::MAX)]);
+        for (expr, error) in [
+            (
+                div(Const(1), sub(var("x"), var("x"))),
+                EvalError::DivisionByZero,
+            ),
+            (add(var("x"), Const(1)), EvalError::Overflow),
+            (
+                div(sub(Const(-1), var("x")), Const(-1)),
+                EvalError::Overflow,
+            ),
+            (
+                Summation(vec![var("x"), var("x"), Const(-5)]),
+                EvalError::Overflow,
+            ),
+            (
+                add(var("x"), var("y")),
+                EvalError::UnboundVariable("y".to_string()),
+            ),
+            // The first error is reported, even if a variable is unbound later on
+            (
+                add(div(var("x"), Const(0)), var("y")),
+                EvalError::DivisionByZero,
+            ),
+            (
+                sigma("i", Const(0), var("x"), Const(0)),
+                EvalError::TooManyIterations,
+            ),
+            (
+                sigma(
+                    "i",
+                    Const(1),
+                    Const(5000),
+                    sigma("j", Const(1), Const(2000), Const(1)),
+                ),
+                EvalError::TooManyIterations,
+            ),
+        ] {
+            assert_eq!(compile(&expr).eval(&env), Err(error.clone()));
+            assert_eq!(eval(&expr, &env), Err(error));
+        }
+        assert_eq!(
+            compile(&Summation(vec![var("x"), Const(1), Const(-2)])).eval(&env),
+            Ok(i64::MAX - 1)
+        );
+        assert_eq!(compile(&Summation(vec![])).eval(&env), Ok(0));
+    }
+
+    #[test]
+    fn test_agrees_with_eval() {
+        let mut rng = StdRng::seed_from_u64(0xb17e);
+        // A single `Vm` is reused for all programs, including after errors
+        let mut vm = Vm::new();
+        for _ in 0..10_000 {
+            let expr = random_expr(&mut rng, 5);
+            let program = compile(&expr);
+            for _ in 0..5 {
+                let env = random_env(&mut rng);
+                assert_eq!(
+                    vm.run_env(&program, &env),
+                    eval(&expr, &env),
+                    "{expr:?} compiled to {:?}, with {env:?}",
+                    program.instrs()
+                );
+            }
+        }
+    }
+}
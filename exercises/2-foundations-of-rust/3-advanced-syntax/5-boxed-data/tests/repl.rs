@@ -0,0 +1,111 @@
+//! Drives the REPL binary through stdin, like a script would
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn run(script: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_repl"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn assert_transcript(script: &str, expected: &str, success: bool) {
+    let output = run(script);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    assert_eq!(output.status.success(), success);
+}
+
+#[test]
+fn test_evaluate_and_assign() {
+    assert_transcript(
+        "\
+1 + 2 * 3
+let x = 6
+let y = x * 7
+
+y - x
+let x = let z = 2 in z * z
+sigma(i, 1, x, i)
+let y = 1 in y + x
+:vars
+",
+        "\
+7
+x = 6
+y = 42
+36
+x = 4
+10
+5
+x = 4
+y = 42
+",
+        true,
+    );
+}
+
+#[test]
+fn test_commands() {
+    assert_transcript(
+        "\
+let x = 2
+:ast (x - 5) * 3
+:simplify 0 + x * 1 + sum(1, y, 2)
+:history
+:quit
+x
+",
+        "\
+x = 2
+Mul(Sub(Var(\"x\"), Const(5)), Const(3))
+x + sum(y, 3)
+   1  let x = 2
+   2  :ast (x - 5) * 3
+   3  :simplify 0 + x * 1 + sum(1, y, 2)
+   4  :history
+",
+        true,
+    );
+}
+
+#[test]
+fn test_errors() {
+    assert_transcript(
+        "\
+1 / 0
+y + 1
+let x = 1 +
+:ast é + #
+let x = 9223372036854775807 + 1
+x
+:nope
+1 + 1
+",
+        "\
+error: division by zero
+error: unbound variable `y`
+error: expected an expression, found end of input at byte 11
+  let x = 1 +
+             ^
+error: expected an expression, found `#` at byte 5
+  é + #
+      ^
+error: arithmetic overflow
+error: unbound variable `x`
+error: unknown command `:nope`, type `:help` for help
+2
+",
+        false,
+    );
+}
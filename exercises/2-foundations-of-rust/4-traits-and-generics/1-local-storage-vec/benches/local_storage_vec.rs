@@ -0,0 +1,91 @@
+use std::hint::black_box;
+
+use criterion::measurement::WallTime;
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion};
+use exercise_a3::LocalStorageVec;
+use smallvec::SmallVec;
+
+/// Benchmark a `LocalStorageVec<u64, N>` against a `Vec<u64>` and a
+/// `SmallVec<[u64; N]>`, which uses the same stack-or-heap layout idea.
+/// Each benchmark is run for several `N`, so that the results can be used
+/// to pick a buffer size for a call site.
+///
+/// `push`, `iterate` and `clone` work with `N` items, which fit on the stack.
+/// `spill` pushes `N + 1` items, which is the cost of moving the items to the heap.
+fn bench_n<const N: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push");
+    bench_push::<N>(&mut group, N);
+    group.finish();
+
+    let mut group = c.benchmark_group("spill");
+    bench_push::<N>(&mut group, N + 1);
+    group.finish();
+
+    let lsv: LocalStorageVec<u64, N> = (0..N as u64).collect();
+    let vec: Vec<u64> = (0..N as u64).collect();
+    let small: SmallVec<[u64; N]> = (0..N as u64).collect();
+
+    let mut group = c.benchmark_group("iterate");
+    group.bench_with_input(BenchmarkId::new("LocalStorageVec", N), &lsv, |b, lsv| {
+        b.iter(|| black_box(lsv).iter().sum::<u64>())
+    });
+    group.bench_with_input(BenchmarkId::new("Vec", N), &vec, |b, vec| {
+        b.iter(|| black_box(vec).iter().sum::<u64>())
+    });
+    group.bench_with_input(BenchmarkId::new("SmallVec", N), &small, |b, small| {
+        b.iter(|| black_box(small).iter().sum::<u64>())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("clone");
+    group.bench_with_input(BenchmarkId::new("LocalStorageVec", N), &lsv, |b, lsv| {
+        b.iter(|| black_box(lsv).clone())
+    });
+    group.bench_with_input(BenchmarkId::new("Vec", N), &vec, |b, vec| {
+        b.iter(|| black_box(vec).clone())
+    });
+    group.bench_with_input(BenchmarkId::new("SmallVec", N), &small, |b, small| {
+        b.iter(|| black_box(small).clone())
+    });
+    group.finish();
+}
+
+/// Push `len` items into an empty list, for a list with a stack buffer of `N` items
+fn bench_push<const N: usize>(group: &mut BenchmarkGroup<'_, WallTime>, len: usize) {
+    group.bench_with_input(BenchmarkId::new("LocalStorageVec", N), &len, |b, &len| {
+        b.iter(|| {
+            let mut lsv: LocalStorageVec<u64, N> = LocalStorageVec::new();
+            for i in 0..black_box(len) as u64 {
+                lsv.push(i);
+            }
+            lsv
+        })
+    });
+    group.bench_with_input(BenchmarkId::new("Vec", N), &len, |b, &len| {
+        b.iter(|| {
+            let mut vec = Vec::new();
+            for i in 0..black_box(len) as u64 {
+                vec.push(i);
+            }
+            vec
+        })
+    });
+    group.bench_with_input(BenchmarkId::new("SmallVec", N), &len, |b, &len| {
+        b.iter(|| {
+            let mut small: SmallVec<[u64; N]> = SmallVec::new();
+            for i in 0..black_box(len) as u64 {
+                small.push(i);
+            }
+            small
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_n::<4>,
+    bench_n::<16>,
+    bench_n::<64>,
+    bench_n::<256>
+);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! Prints the size of a `LocalStorageVec` for a couple of item types and
+//! buffer sizes, next to the size of the alternatives.
+//! Run with `cargo run --example sizes`.
+
+use std::mem::size_of;
+
+use exercise_a3::{LocalStorageString, LocalStorageVec};
+use smallvec::SmallVec;
+
+fn print_sizes<T, const N: usize>() {
+    println!(
+        "{:<8} {:>4} {:>16} {:>9} {:>4}",
+        std::any::type_name::<T>().rsplit("::").next().unwrap(),
+        N,
+        size_of::<LocalStorageVec<T, N>>(),
+        size_of::<SmallVec<[T; N]>>(),
+        size_of::<Vec<T>>(),
+    );
+}
+
+fn main() {
+    println!(
+        "{:<8} {:>4} {:>16} {:>9} {:>4}",
+        "T", "N", "LocalStorageVec", "SmallVec", "Vec"
+    );
+    print_sizes::<u8, 4>();
+    print_sizes::<u8, 16>();
+    print_sizes::<u8, 24>();
+    print_sizes::<u8, 64>();
+    print_sizes::<u32, 4>();
+    print_sizes::<u32, 16>();
+    print_sizes::<u32, 64>();
+    print_sizes::<u64, 4>();
+    print_sizes::<u64, 16>();
+    print_sizes::<u64, 64>();
+    print_sizes::<String, 4>();
+    print_sizes::<String, 16>();
+    println!();
+
+    println!(
+        "{:<24} {:>4}",
+        "LocalStorageString<16>",
+        size_of::<LocalStorageString<16>>()
+    );
+    println!(
+        "{:<24} {:>4}",
+        "LocalStorageString<24>",
+        size_of::<LocalStorageString<24>>()
+    );
+    println!("{:<24} {:>4}", "String", size_of::<String>());
+}
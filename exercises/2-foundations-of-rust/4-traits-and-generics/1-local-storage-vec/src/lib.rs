@@ -1,60 +1,955 @@
+//! A `Vec`-like list that keeps a small number of items on the stack.
+//!
+//! The crate is `no_std`. With the `alloc` feature, which is enabled by default,
+//! a [`LocalStorageVec`] moves its items to the heap when they no longer fit on
+//! the stack. Without it, a [`LocalStorageVec`] has a fixed capacity of `N` items,
+//! and [`LocalStorageVec::push`] and [`LocalStorageVec::insert`] hand back the item
+//! if it doesn't fit. Note that this changes the signatures of those methods.
+//!
+//! Both configurations are tested on the host, by running `cargo test` and
+//! `cargo test --no-default-features`. To check the unsafe code for undefined
+//! behaviour and leaks, run the same tests under Miri, with `cargo +nightly miri test`
+//! and `cargo +nightly miri test --no-default-features`.
+//!
+//! `cargo bench` compares pushing, iterating and cloning a [`LocalStorageVec`]
+//! with a `Vec` and a `SmallVec` for several `N`, and `cargo run --example sizes`
+//! prints how much memory each of them takes up.
+
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(test)]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+use core::ops::{Bound, Deref, DerefMut, Index, IndexMut, Range, RangeBounds, RangeFrom, RangeTo};
+use core::{fmt, ptr, slice};
+
+#[cfg(feature = "serde")]
+mod serde;
+mod string;
+
+pub use string::LocalStorageString;
+
 /// A growable, generic list that resides on the stack if it's small,
 /// but is moved to the heap to grow larger if needed.
 /// This list is generic over the items it contains as well as the
 /// size of its buffer if it's on the stack.
-pub enum LocalStorageVec<T, const N: usize> {
-    // TODO add some variants containing data
-    // to make the compiler happy
+///
+/// Once the items have been moved to the heap, they stay there, unless
+/// [`LocalStorageVec::shrink_to_inline`] is called. Alternatively, `SHRINK_BELOW`
+/// sets a policy to move them back to the stack automatically as soon as
+/// fewer than `SHRINK_BELOW` items are left. The default of 0 never shrinks.
+/// As the items are moved to the heap when there are more than `N` of them,
+/// choosing `SHRINK_BELOW` well below `N` prevents a `LocalStorageVec` that
+/// hovers around `N` items from moving back and forth all the time.
+/// `SHRINK_BELOW` must not be greater than `N`.
+///
+/// Without the `alloc` feature, there is no `Heap` variant, and the
+/// `LocalStorageVec` can hold at most `N` items.
+pub enum LocalStorageVec<T, const N: usize, const SHRINK_BELOW: usize = 0> {
+    /// The items are stored inline. Only the first `len` items of `buf`
+    /// are initialized; the rest of the buffer is uninitialized memory.
+    Stack {
+        buf: [MaybeUninit<T>; N],
+        len: usize,
+    },
+    /// The items did not fit in `buf` and were moved to the heap
+    #[cfg(feature = "alloc")]
+    Heap(Vec<T>),
 }
 
-// **Below `From` implementation is used in the tests and are therefore given. However,
-// you should have a thorough look at it as they contain various new concepts.**
+// **Have a thorough look at the `From` implementations below, as they contain various new concepts.**
 // This implementation is generic not only over the type `T`, but also over the
 // constants `N` and 'M', allowing us to support conversions from arrays of any
 // length to `LocalStorageVec`s of with any stack buffer size.
 // In Rust, we call this feature 'const generics'
-impl<T, const N: usize, const M: usize> From<[T; N]> for LocalStorageVec<T, M>
-where
-    // We require that `T` implement `Default`, in case we need to fill up our
-    // stack-based array without resorting to uninitialized memory. Once
-    // we are more proficient in working with uninitialized memory, we'll be
-    // able to remove this bound.
-    T: Default,
-{
+impl<T, const N: usize, const M: usize, const S: usize> From<[T; N]> for LocalStorageVec<T, M, S> {
     fn from(array: [T; N]) -> Self {
-        if N <= M {
-            // In this case, the passed array should fit on the stack.
-
-            // We crate an `Iterator` of the passed array,
-            let mut it = array.into_iter();
-            Self::Stack {
-                // This is a trick for copying an array into another one that's
-                // at least as long as the original, without having to create
-                // default values more than strictly necessary. The `[(); M]`
-                // array is zero-sized, meaning there's no cost to instantiate it.
-                // The `map` call iterates over each of its items, and maps them to
-                // the next item from the `array` passed to this function. If there
-                // are no more items left from `array`, we insert the default specified
-                // for `T`
-                buf: [(); M].map(|_| it.next().unwrap_or_default()),
-                // The length of the buffer on stack is the length of the original `array`: `N`
-                len: N,
+        // If the passed array does not fit, we'll resort to moving it to the heap instead
+        #[cfg(feature = "alloc")]
+        if N > M {
+            return Self::Heap(Vec::from(array));
+        }
+        // Without a heap to move to, an array that is too large is rejected at compile time
+        #[cfg(not(feature = "alloc"))]
+        const {
+            assert!(N <= M, "the array does not fit in the LocalStorageVec");
+        }
+
+        // In this case, the passed array should fit on the stack.
+        // We don't need a `T: Default` bound to pad the buffer: the slots
+        // after the items from `array` are simply left uninitialized.
+        let mut vec = Self::new();
+        for item in array {
+            // This never spills, as we've just checked that `N <= M`
+            vec.push_or_panic(item);
+        }
+        vec
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, const N: usize, const S: usize> From<Vec<T>> for LocalStorageVec<T, N, S> {
+    fn from(v: Vec<T>) -> Self {
+        if v.len() <= N {
+            v.into_iter().collect()
+        } else {
+            Self::Heap(v)
+        }
+    }
+}
+
+impl<T, const N: usize, const S: usize> LocalStorageVec<T, N, S> {
+    /// Checked at compile time whenever a `LocalStorageVec` is created
+    const VALID_SHRINK_POLICY: () = assert!(
+        S <= N,
+        "SHRINK_BELOW must not be greater than the stack buffer size N"
+    );
+
+    /// Creates a new, empty `LocalStorageVec` without allocating
+    pub const fn new() -> Self {
+        let () = Self::VALID_SHRINK_POLICY;
+        Self::Stack {
+            buf: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Stack { len, .. } => *len,
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of items this `LocalStorageVec` can hold without (re)allocating
+    pub fn capacity(&self) -> usize {
+        match self {
+            Self::Stack { .. } => N,
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => v.capacity(),
+        }
+    }
+
+    /// Appends an item, moving the contents to the heap if they no longer fit on the stack
+    #[cfg(feature = "alloc")]
+    pub fn push(&mut self, value: T) {
+        if let Err(value) = self.push_inline(value) {
+            self.spill(1).push(value);
+        }
+    }
+
+    /// Appends an item, or hands it back in an `Err` if the `LocalStorageVec` is full
+    #[cfg(not(feature = "alloc"))]
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        self.push_inline(value)
+    }
+
+    /// Appends an item if it fits on the stack, without spilling to the heap
+    fn push_inline(&mut self, value: T) -> Result<(), T> {
+        match self {
+            Self::Stack { buf, len } if *len < N => {
+                buf[*len].write(value);
+                *len += 1;
+                Ok(())
+            }
+            _ => Err(value),
+        }
+    }
+
+    /// Appends an item for methods that can't hand it back if it doesn't fit
+    ///
+    /// # Panics
+    /// Panics if the `LocalStorageVec` is full and the `alloc` feature is disabled
+    fn push_or_panic(&mut self, value: T) {
+        #[cfg(feature = "alloc")]
+        self.push(value);
+        #[cfg(not(feature = "alloc"))]
+        if self.push(value).is_err() {
+            panic!("LocalStorageVec capacity of {N} exceeded");
+        }
+    }
+
+    /// Removes the last item and returns it, or `None` if the `LocalStorageVec` is empty
+    pub fn pop(&mut self) -> Option<T> {
+        match self {
+            Self::Stack { len: 0, .. } => None,
+            Self::Stack { buf, len } => {
+                *len -= 1;
+                // SAFETY: `buf[len]` was initialized, and we've just decremented `len`
+                // so it will not be read or dropped again.
+                Some(unsafe { buf[*len].assume_init_read() })
+            }
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => {
+                let item = v.pop();
+                self.apply_shrink_policy();
+                item
+            }
+        }
+    }
+
+    /// Inserts an item at position `index`, shifting all items after it to the right
+    ///
+    /// # Panics
+    /// Panics if `index > len`
+    #[cfg(feature = "alloc")]
+    pub fn insert(&mut self, index: usize, value: T) {
+        if let Err(value) = self.insert_inline(index, value) {
+            self.spill(1).insert(index, value);
+        }
+    }
+
+    /// Inserts an item at position `index`, shifting all items after it to the right,
+    /// or hands it back in an `Err` if the `LocalStorageVec` is full
+    ///
+    /// # Panics
+    /// Panics if `index > len`
+    #[cfg(not(feature = "alloc"))]
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), T> {
+        self.insert_inline(index, value)
+    }
+
+    /// Inserts an item if it fits on the stack, without spilling to the heap
+    fn insert_inline(&mut self, index: usize, value: T) -> Result<(), T> {
+        let length = self.len();
+        assert!(
+            index <= length,
+            "insertion index (is {index}) should be <= len (is {length})"
+        );
+        match self {
+            Self::Stack { buf, len } if *len < N => {
+                // SAFETY: the items in `index..len` are initialized and the
+                // buffer has room for one more item, so moving them one slot to
+                // the right stays in bounds. Afterwards, slot `index` is logically
+                // uninitialized and is overwritten without dropping.
+                unsafe {
+                    let p = buf.as_mut_ptr().add(index);
+                    ptr::copy(p, p.add(1), *len - index);
+                }
+                buf[index].write(value);
+                *len += 1;
+                Ok(())
+            }
+            _ => Err(value),
+        }
+    }
+
+    /// Removes and returns the item at position `index`, shifting all items after it to the left
+    ///
+    /// # Panics
+    /// Panics if `index >= len`
+    pub fn remove(&mut self, index: usize) -> T {
+        let length = self.len();
+        assert!(
+            index < length,
+            "removal index (is {index}) should be < len (is {length})"
+        );
+        match self {
+            Self::Stack { buf, len } => {
+                // SAFETY: `buf[index]` is initialized. We move it out and then
+                // shift the initialized items in `index + 1..len` one slot to the left,
+                // so each item is still owned exactly once.
+                unsafe {
+                    let value = buf[index].assume_init_read();
+                    let p = buf.as_mut_ptr().add(index);
+                    ptr::copy(p.add(1), p, *len - index - 1);
+                    *len -= 1;
+                    value
+                }
+            }
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => {
+                let item = v.remove(index);
+                self.apply_shrink_policy();
+                item
+            }
+        }
+    }
+
+    /// Removes all items
+    pub fn clear(&mut self) {
+        match self {
+            Self::Stack { buf, len } => {
+                let old_len = *len;
+                // Set the length first: if dropping an item panics, the
+                // remaining items are leaked rather than dropped twice.
+                *len = 0;
+                // SAFETY: the first `old_len` items were initialized and are
+                // no longer reachable now that `len` is 0.
+                unsafe {
+                    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                        buf.as_mut_ptr().cast::<T>(),
+                        old_len,
+                    ))
+                }
+            }
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => {
+                v.clear();
+                self.apply_shrink_policy();
+            }
+        }
+    }
+
+    /// Makes sure there's room for at least `additional` more items, moving
+    /// the contents to the heap if they would no longer fit on the stack
+    ///
+    /// # Panics
+    /// Panics if the items won't fit and the `alloc` feature is disabled,
+    /// or if the new capacity overflows a `usize`, like [`Vec::reserve`]
+    pub fn reserve(&mut self, additional: usize) {
+        match self {
+            Self::Stack { len, .. } if len.checked_add(additional).is_some_and(|n| n <= N) => {}
+            #[cfg(feature = "alloc")]
+            Self::Stack { .. } => {
+                self.spill(additional);
+            }
+            #[cfg(not(feature = "alloc"))]
+            Self::Stack { .. } => panic!("LocalStorageVec capacity of {N} exceeded"),
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => v.reserve(additional),
+        }
+    }
+
+    /// Shortens the `LocalStorageVec` to `new_len` items, dropping the rest.
+    /// Has no effect if `new_len` is greater than the current length.
+    pub fn truncate(&mut self, new_len: usize) {
+        match self {
+            Self::Stack { buf, len } if new_len < *len => {
+                let old_len = *len;
+                *len = new_len;
+                // SAFETY: the items in `new_len..old_len` were initialized and are
+                // no longer reachable now that `len` is `new_len`.
+                unsafe {
+                    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                        buf.as_mut_ptr().cast::<T>().add(new_len),
+                        old_len - new_len,
+                    ))
+                }
+            }
+            Self::Stack { .. } => {}
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => {
+                v.truncate(new_len);
+                self.apply_shrink_policy();
+            }
+        }
+    }
+
+    /// Keeps only the items for which `f` returns `true`, preserving their order
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain_mut(|item| f(item))
+    }
+
+    /// Like [`LocalStorageVec::retain`], but passes a mutable reference to `f`
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        match self {
+            Self::Stack { .. } => {
+                // All items fit on the stack, so pushing the ones we keep
+                // back onto the emptied `self` never spills
+                for mut item in core::mem::take(self) {
+                    if f(&mut item) {
+                        self.push_or_panic(item);
+                    }
+                }
+            }
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => {
+                v.retain_mut(f);
+                self.apply_shrink_policy();
+            }
+        }
+    }
+
+    /// Removes consecutive items for which `same_bucket` returns `true`. The first
+    /// argument passed to `same_bucket` is the candidate for removal, the second one
+    /// is the item that was kept before it.
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        match self {
+            Self::Stack { .. } => {
+                for mut item in core::mem::take(self) {
+                    let duplicate = self
+                        .last_mut()
+                        .is_some_and(|last| same_bucket(&mut item, last));
+                    if !duplicate {
+                        self.push_or_panic(item);
+                    }
+                }
+            }
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => {
+                v.dedup_by(same_bucket);
+                self.apply_shrink_policy();
+            }
+        }
+    }
+
+    /// Removes consecutive items that map to the same key
+    pub fn dedup_by_key<F, K>(&mut self, mut key: F)
+    where
+        F: FnMut(&mut T) -> K,
+        K: PartialEq,
+    {
+        self.dedup_by(|a, b| key(a) == key(b))
+    }
+
+    /// Removes the items in `range`, returning them in an iterator. The items
+    /// that are not consumed by the iterator are dropped along with it.
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds, or if its start is greater than its end
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, T, N, S>
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.len();
+        let Range { start, end } = to_range(range, len);
+        // SAFETY: the first `start` items are initialized. The `Drain` is now
+        // responsible for the items in `start..len`; if it's leaked, those
+        // are leaked as well, but never dropped twice.
+        unsafe { self.set_len(start) };
+        Drain {
+            vec: self,
+            counter: start,
+            end,
+            tail_start: end,
+            tail_len: len - end,
+        }
+    }
+
+    /// Replaces the items in `range` with the items from `replace_with`,
+    /// returning the removed items in an iterator
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds, or if its start is greater than its end.
+    /// Without the `alloc` feature, also panics if the resulting items don't fit.
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> LocalStorageVecIter<T, N, S>
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = T>,
+    {
+        let Range { start, end } = to_range(range, self.len());
+        let removed: Self = self.drain(start..end).collect();
+        let mut tail = self.split_off(start);
+        self.extend(replace_with);
+        self.append(&mut tail);
+        removed.into_iter()
+    }
+
+    /// Splits off the items from `at` onwards into a new `LocalStorageVec`, which
+    /// resides on the stack if they fit
+    ///
+    /// # Panics
+    /// Panics if `at > len`
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let length = self.len();
+        assert!(
+            at <= length,
+            "`at` split index (is {at}) should be <= len (is {length})"
+        );
+        self.drain(at..).collect()
+    }
+
+    /// Moves all items of `other` to the end of `self`, leaving `other` empty
+    ///
+    /// # Panics
+    /// Panics if the items don't fit and the `alloc` feature is disabled
+    pub fn append<const M: usize, const S2: usize>(
+        &mut self,
+        other: &mut LocalStorageVec<T, M, S2>,
+    ) {
+        self.extend(other.drain(..));
+    }
+
+    /// Moves the items back to the stack if they fit, freeing the heap allocation
+    #[cfg(feature = "alloc")]
+    pub fn shrink_to_inline(&mut self) {
+        if let Self::Heap(v) = self {
+            if v.len() <= N {
+                let len = v.len();
+                let mut buf = [const { MaybeUninit::uninit() }; N];
+                // SAFETY: `buf` has room for `len` items. We move the items out of
+                // `v` and set its length to 0, so that they're not dropped along
+                // with it when we overwrite the `Heap` variant.
+                unsafe {
+                    ptr::copy_nonoverlapping(v.as_ptr(), buf.as_mut_ptr().cast::<T>(), len);
+                    v.set_len(0);
+                }
+                *self = Self::Stack { buf, len };
+            }
+        }
+    }
+
+    /// Moves the items back to the stack if there are fewer than `SHRINK_BELOW` left
+    #[cfg(feature = "alloc")]
+    fn apply_shrink_policy(&mut self) {
+        let () = Self::VALID_SHRINK_POLICY;
+        if matches!(self, Self::Heap(v) if v.len() < S) {
+            self.shrink_to_inline();
+        }
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_ref().iter()
+    }
+
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.as_mut().iter_mut()
+    }
+
+    /// Moves the items to a `Vec` with room for at least `additional` more items,
+    /// turning `self` into the `Heap` variant, and returns that `Vec`
+    #[cfg(feature = "alloc")]
+    fn spill(&mut self, additional: usize) -> &mut Vec<T> {
+        if let Self::Stack { buf, len } = self {
+            let capacity = len.checked_add(additional).expect("capacity overflow");
+            let mut v = Vec::with_capacity(capacity);
+            // SAFETY: the first `len` items of `buf` are initialized. We move them
+            // into `v` and set `len` to 0, so that they're not dropped by the
+            // `Stack` variant we're about to overwrite.
+            unsafe {
+                ptr::copy_nonoverlapping(buf.as_ptr().cast::<T>(), v.as_mut_ptr(), *len);
+                v.set_len(*len);
+            }
+            *len = 0;
+            *self = Self::Heap(v);
+        }
+        match self {
+            Self::Heap(v) => v,
+            Self::Stack { .. } => unreachable!(),
+        }
+    }
+
+    fn as_ptr(&self) -> *const T {
+        match self {
+            Self::Stack { buf, .. } => buf.as_ptr().cast(),
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => v.as_ptr(),
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        match self {
+            Self::Stack { buf, .. } => buf.as_mut_ptr().cast(),
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => v.as_mut_ptr(),
+        }
+    }
+
+    /// # Safety
+    /// `new_len` must be at most the capacity, and the first `new_len` items
+    /// must be initialized.
+    unsafe fn set_len(&mut self, new_len: usize) {
+        match self {
+            Self::Stack { len, .. } => *len = new_len,
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => v.set_len(new_len),
+        }
+    }
+}
+
+impl<T: Clone, const N: usize, const S: usize> LocalStorageVec<T, N, S> {
+    /// Clones and appends all items in `other`
+    ///
+    /// # Panics
+    /// Panics if the items don't fit and the `alloc` feature is disabled
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        self.reserve(other.len());
+        for item in other {
+            self.push_or_panic(item.clone());
+        }
+    }
+
+    /// Resizes the `LocalStorageVec` to `new_len` items, either by truncating it
+    /// or by appending clones of `value`
+    ///
+    /// # Panics
+    /// Panics if `new_len` exceeds `N` and the `alloc` feature is disabled
+    pub fn resize(&mut self, new_len: usize, value: T) {
+        let len = self.len();
+        if new_len > len {
+            self.reserve(new_len - len);
+            for _ in len + 1..new_len {
+                self.push_or_panic(value.clone());
             }
+            self.push_or_panic(value);
         } else {
-            // If the passed array does not fit, we'll resort to moving it to the heap instead
-            Self::Heap(Vec::from(array))
+            self.truncate(new_len);
+        }
+    }
+}
+
+impl<T: PartialEq, const N: usize, const S: usize> LocalStorageVec<T, N, S> {
+    /// Removes consecutive repeated items
+    pub fn dedup(&mut self) {
+        self.dedup_by(|a, b| a == b)
+    }
+}
+
+/// Converts any of the range types into a `Range`, checking it against `len`
+fn to_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start
+            .checked_add(1)
+            .expect("attempted to index slice from after maximum usize"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end
+            .checked_add(1)
+            .expect("attempted to index slice up to maximum usize"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(
+        start <= end,
+        "slice index starts at {start} but ends at {end}"
+    );
+    assert!(
+        end <= len,
+        "range end index {end} out of range for slice of length {len}"
+    );
+    start..end
+}
+
+impl<T, const N: usize, const S: usize> Drop for LocalStorageVec<T, N, S> {
+    fn drop(&mut self) {
+        match self {
+            Self::Stack { .. } => self.clear(),
+            // The `Heap` variant drops its items along with the `Vec`
+            #[cfg(feature = "alloc")]
+            Self::Heap(_) => {}
+        }
+    }
+}
+
+impl<T, const N: usize, const S: usize> Default for LocalStorageVec<T, N, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone, const N: usize, const S: usize> Clone for LocalStorageVec<T, N, S> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Stack { .. } => {
+                let mut vec = Self::new();
+                for item in self.iter() {
+                    vec.push_or_panic(item.clone());
+                }
+                vec
+            }
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => Self::Heap(v.clone()),
+        }
+    }
+}
+
+impl<T: fmt::Debug, const N: usize, const S: usize> fmt::Debug for LocalStorageVec<T, N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize, const S: usize, const M: usize, const S2: usize>
+    PartialEq<LocalStorageVec<T, M, S2>> for LocalStorageVec<T, N, S>
+{
+    fn eq(&self, other: &LocalStorageVec<T, M, S2>) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl<T: Eq, const N: usize, const S: usize> Eq for LocalStorageVec<T, N, S> {}
+
+impl<T, const N: usize, const S: usize> AsRef<[T]> for LocalStorageVec<T, N, S> {
+    fn as_ref(&self) -> &[T] {
+        match self {
+            // SAFETY: the first `len` items of `buf` are initialized
+            Self::Stack { buf, len } => unsafe { slice::from_raw_parts(buf.as_ptr().cast(), *len) },
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => v,
+        }
+    }
+}
+
+impl<T, const N: usize, const S: usize> AsMut<[T]> for LocalStorageVec<T, N, S> {
+    fn as_mut(&mut self) -> &mut [T] {
+        match self {
+            // SAFETY: the first `len` items of `buf` are initialized
+            Self::Stack { buf, len } => unsafe {
+                slice::from_raw_parts_mut(buf.as_mut_ptr().cast(), *len)
+            },
+            #[cfg(feature = "alloc")]
+            Self::Heap(v) => v,
+        }
+    }
+}
+
+impl<T, const N: usize, const S: usize> Deref for LocalStorageVec<T, N, S> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        <Self as AsRef<[T]>>::as_ref(self)
+    }
+}
+
+impl<T, const N: usize, const S: usize> DerefMut for LocalStorageVec<T, N, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        <Self as AsMut<[T]>>::as_mut(self)
+    }
+}
+
+/// Marker trait for the types a `LocalStorageVec` can be indexed with
+pub trait LocalStorageVecIndex {}
+
+impl LocalStorageVecIndex for usize {}
+impl LocalStorageVecIndex for RangeTo<usize> {}
+impl LocalStorageVecIndex for RangeFrom<usize> {}
+impl LocalStorageVecIndex for Range<usize> {}
+
+impl<T, I, const N: usize, const S: usize> Index<I> for LocalStorageVec<T, N, S>
+where
+    I: LocalStorageVecIndex,
+    [T]: Index<I>,
+{
+    type Output = <[T] as Index<I>>::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        self.as_ref().index(index)
+    }
+}
+
+impl<T, I, const N: usize, const S: usize> IndexMut<I> for LocalStorageVec<T, N, S>
+where
+    I: LocalStorageVecIndex,
+    [T]: IndexMut<I>,
+{
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        self.as_mut().index_mut(index)
+    }
+}
+
+/// An iterator that moves the items out of a `LocalStorageVec`
+pub struct LocalStorageVecIter<T, const N: usize, const S: usize = 0> {
+    /// The vec whose items we're moving out. Its length is set to 0 so it
+    /// doesn't drop any items itself; the items in `counter..end` are still
+    /// initialized and owned by this iterator.
+    vec: LocalStorageVec<T, N, S>,
+    counter: usize,
+    end: usize,
+}
+
+impl<T, const N: usize, const S: usize> Iterator for LocalStorageVecIter<T, N, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.counter == self.end {
+            return None;
+        }
+        // SAFETY: the item at `counter` is initialized and owned by the iterator,
+        // and incrementing `counter` makes sure we don't read it again.
+        let item = unsafe { self.vec.as_ptr().add(self.counter).read() };
+        self.counter += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.counter;
+        (len, Some(len))
+    }
+}
+
+impl<T, const N: usize, const S: usize> DoubleEndedIterator for LocalStorageVecIter<T, N, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.counter == self.end {
+            return None;
+        }
+        self.end -= 1;
+        // SAFETY: the item at `end` is initialized and owned by the iterator,
+        // and decrementing `end` makes sure we don't read it again.
+        Some(unsafe { self.vec.as_ptr().add(self.end).read() })
+    }
+}
+
+impl<T, const N: usize, const S: usize> ExactSizeIterator for LocalStorageVecIter<T, N, S> {}
+
+impl<T, const N: usize, const S: usize> Drop for LocalStorageVecIter<T, N, S> {
+    fn drop(&mut self) {
+        // SAFETY: the items in `counter..end` have not been yielded yet,
+        // so we are responsible for dropping them
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                self.vec.as_mut_ptr().add(self.counter),
+                self.end - self.counter,
+            ))
+        }
+    }
+}
+
+impl<T, const N: usize, const S: usize> IntoIterator for LocalStorageVec<T, N, S> {
+    type Item = T;
+    type IntoIter = LocalStorageVecIter<T, N, S>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let end = self.len();
+        // SAFETY: a length of 0 is always valid. From now on, the iterator
+        // is responsible for the items.
+        unsafe { self.set_len(0) };
+        LocalStorageVecIter {
+            vec: self,
+            counter: 0,
+            end,
+        }
+    }
+}
+
+impl<'a, T, const N: usize, const S: usize> IntoIterator for &'a LocalStorageVec<T, N, S> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize, const S: usize> IntoIterator for &'a mut LocalStorageVec<T, N, S> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Without the `alloc` feature, extending a `LocalStorageVec` beyond `N` items panics
+impl<T, const N: usize, const S: usize> Extend<T> for LocalStorageVec<T, N, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        // Spill at most once, if we know up front that the items won't fit
+        self.reserve(iter.size_hint().0);
+        for item in iter {
+            self.push_or_panic(item);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a, const N: usize, const S: usize> Extend<&'a T> for LocalStorageVec<T, N, S> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+impl<T, const N: usize, const S: usize> FromIterator<T> for LocalStorageVec<T, N, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+/// An iterator that removes a range of items from a `LocalStorageVec`,
+/// created by [`LocalStorageVec::drain`]
+pub struct Drain<'a, T, const N: usize, const S: usize = 0> {
+    /// The vec we're draining. Its length is set to the start of the
+    /// drained range while the `Drain` exists.
+    vec: &'a mut LocalStorageVec<T, N, S>,
+    /// The items in `counter..end` are yet to be yielded
+    counter: usize,
+    end: usize,
+    /// The items after the drained range, which are moved back
+    /// into place when the `Drain` is dropped
+    tail_start: usize,
+    tail_len: usize,
+}
+
+impl<T, const N: usize, const S: usize> Iterator for Drain<'_, T, N, S> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.counter == self.end {
+            return None;
         }
+        // SAFETY: the item at `counter` is initialized and owned by the `Drain`,
+        // and incrementing `counter` makes sure we don't read it again.
+        let item = unsafe { self.vec.as_ptr().add(self.counter).read() };
+        self.counter += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.counter;
+        (len, Some(len))
+    }
+}
+
+impl<T, const N: usize, const S: usize> DoubleEndedIterator for Drain<'_, T, N, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.counter == self.end {
+            return None;
+        }
+        self.end -= 1;
+        // SAFETY: the item at `end` is initialized and owned by the `Drain`,
+        // and decrementing `end` makes sure we don't read it again.
+        Some(unsafe { self.vec.as_ptr().add(self.end).read() })
+    }
+}
+
+impl<T, const N: usize, const S: usize> ExactSizeIterator for Drain<'_, T, N, S> {}
+
+impl<T, const N: usize, const S: usize> Drop for Drain<'_, T, N, S> {
+    fn drop(&mut self) {
+        let start = self.vec.len();
+        let base = self.vec.as_mut_ptr();
+        // SAFETY: the items in `counter..end` have not been yielded yet, so we
+        // drop them. The tail is initialized, and moving it to directly after
+        // the first `start` items leaves exactly `start + tail_len` initialized items.
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                base.add(self.counter),
+                self.end - self.counter,
+            ));
+            ptr::copy(base.add(self.tail_start), base.add(start), self.tail_len);
+            self.vec.set_len(start + self.tail_len);
+        }
+        #[cfg(feature = "alloc")]
+        self.vec.apply_shrink_policy();
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::LocalStorageVec;
+    use std::cell::Cell;
+    use std::prelude::rust_2021::*;
+    use std::rc::Rc;
+    #[cfg(feature = "alloc")]
+    use std::{dbg, mem::MaybeUninit, vec};
 
     #[test]
     // Don't remove the #[ignore] attribute or your tests will take forever!
     #[ignore = "This test is just to validate the definition of `LocalStorageVec`. If it compiles, all is OK"]
-    #[allow(unreachable_code, unused_variables)]
+    #[allow(unreachable_code, unused_variables, clippy::empty_loop)]
+    #[cfg(feature = "alloc")]
     fn it_compiles() {
         // Here's a trick to 'initialize' a type while not actually
         // creating a value: an infinite `loop` expression diverges
@@ -66,219 +961,678 @@ mod test {
         // - https://doc.rust-lang.org/rust-by-example/fn/diverging.html
         // - https://doc.rust-lang.org/reference/expressions/loop-expr.html#infinite-loops
         let vec: LocalStorageVec<u32, 10> = loop {};
-        match vec {
+        // `LocalStorageVec` implements `Drop`, so we can't move its fields
+        // out, but we can still match on a reference to it
+        match &vec {
             LocalStorageVec::Stack { buf, len } => {
-                let _buf: [u32; 10] = buf;
-                let _len: usize = len;
+                let _buf: &[MaybeUninit<u32>; 10] = buf;
+                let _len: &usize = len;
             }
             LocalStorageVec::Heap(v) => {
-                let _v: Vec<u32> = v;
+                let _v: &Vec<u32> = v;
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_from_vecs() {
+        // The `vec!` macro creates a `Vec<T>` in a way that resembles
+        // array-initialization syntax.
+
+        let vec: LocalStorageVec<usize, 10> = LocalStorageVec::from(vec![1, 2, 3]);
+        // Assert that the call to `from` indeed yields a `Stack` variant
+        assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: _ }));
+
+        let vec: LocalStorageVec<usize, 2> = LocalStorageVec::from(vec![1, 2, 3]);
+        // Assert that the call to `from` indeed yields a `Heap` variant
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_as_refs() {
+        let vec: LocalStorageVec<i32, 256> = LocalStorageVec::from([0; 128]);
+        let slice: &[i32] = vec.as_ref();
+        assert!(slice.len() == 128);
+        let vec: LocalStorageVec<i32, 32> = LocalStorageVec::from([0; 128]);
+        let slice: &[i32] = vec.as_ref();
+        assert!(slice.len() == 128);
+
+        let mut vec: LocalStorageVec<i32, 256> = LocalStorageVec::from([0; 128]);
+        let slice_mut: &[i32] = vec.as_mut();
+        assert!(slice_mut.len() == 128);
+        let mut vec: LocalStorageVec<i32, 32> = LocalStorageVec::from([0; 128]);
+        let slice_mut: &[i32] = vec.as_mut();
+        assert!(slice_mut.len() == 128);
+    }
+
+    #[test]
+    fn it_constructs() {
+        let vec: LocalStorageVec<usize, 10> = LocalStorageVec::new();
+        // Assert that the call to `new` indeed yields a `Stack` variant with zero length
+        assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: 0 }));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_lens() {
+        let vec: LocalStorageVec<_, 3> = LocalStorageVec::from([0, 1, 2]);
+        assert_eq!(vec.len(), 3);
+        let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
+        assert_eq!(vec.len(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_pushes() {
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::new();
+        for value in 0..128 {
+            vec.push(value);
+        }
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 128, .. }));
+        for value in 128..256 {
+            vec.push(value);
+        }
+        assert!(matches!(vec, LocalStorageVec::Heap(ref v) if v.len() == 256))
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_pops() {
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
+        for _ in 0..128 {
+            assert_eq!(vec.pop(), Some(0))
+        }
+        assert_eq!(vec.pop(), None);
+
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 256]);
+        for _ in 0..256 {
+            assert_eq!(vec.pop(), Some(0))
+        }
+        assert_eq!(vec.pop(), None);
+
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from(vec![0; 256]);
+        for _ in 0..256 {
+            assert_eq!(vec.pop(), Some(0))
+        }
+        assert_eq!(vec.pop(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_inserts() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
+        vec.insert(1, 3);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 3, 1, 2]);
+
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3]);
+        vec.insert(1, 3);
+        assert!(matches!(vec, LocalStorageVec::Heap { .. }));
+        assert_eq!(vec.as_ref(), &[0, 3, 1, 2, 3]);
+
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3, 4]);
+        vec.insert(1, 3);
+        assert!(matches!(vec, LocalStorageVec::Heap { .. }));
+        assert_eq!(vec.as_ref(), &[0, 3, 1, 2, 3, 4])
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_removes() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
+        let elem = vec.remove(1);
+        dbg!(&vec);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(vec.as_ref(), &[0, 2]);
+        assert_eq!(elem, 1);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
+        let elem = vec.remove(1);
+        assert!(matches!(vec, LocalStorageVec::Heap(..)));
+        assert_eq!(vec.as_ref(), &[0, 2]);
+        assert_eq!(elem, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_clears() {
+        let mut vec: LocalStorageVec<_, 10> = LocalStorageVec::from([0, 1, 2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: 4 }));
+        vec.clear();
+        assert_eq!(vec.len(), 0);
+
+        let mut vec: LocalStorageVec<_, 3> = LocalStorageVec::from([0, 1, 2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        vec.clear();
+        assert_eq!(vec.len(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_iters() {
+        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 32]);
+        let mut iter = vec.into_iter();
+        for item in &mut iter {
+            assert_eq!(item, 0);
+        }
+        assert_eq!(iter.next(), None);
+
+        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from(vec![0; 128]);
+        let mut iter = vec.into_iter();
+        for item in &mut iter {
+            assert_eq!(item, 0);
+        }
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn it_indexes() {
+        let vec: LocalStorageVec<i32, 10> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        assert_eq!(vec[1], 1);
+        assert_eq!(vec[..2], [0, 1]);
+        assert_eq!(vec[4..], [4, 5]);
+        assert_eq!(vec[1..3], [1, 2]);
+    }
+
+    #[test]
+    fn it_borrowing_iters() {
+        let vec: LocalStorageVec<String, 10> = LocalStorageVec::from([
+            "0".to_owned(),
+            "1".to_owned(),
+            "2".to_owned(),
+            "3".to_owned(),
+            "4".to_owned(),
+            "5".to_owned(),
+        ]);
+        let iter = vec.iter();
+        for _ in iter {}
+        // This requires the `vec` not to be consumed by the call to `iter()`
+        drop(vec);
+    }
+
+    #[test]
+    #[allow(unused_variables)]
+    fn it_derefs() {
+        use std::ops::{Deref, DerefMut};
+        let vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
+        // `chunks` is a method that's defined for slices `[T]`, that we can use thanks to `Deref`
+        let chunks = vec.chunks(4);
+        let slice: &[_] = vec.deref();
+
+        let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
+        let chunks = vec.chunks_mut(4);
+        let slice: &mut [_] = vec.deref_mut();
+    }
+
+    /// An item that is neither `Default`, `Copy` nor `Clone`, and that
+    /// counts how often it is dropped. If the tests below are run using
+    /// `cargo +nightly miri test`, Miri additionally checks that we never
+    /// read uninitialized memory.
+    struct Tracked {
+        id: usize,
+        drops: Rc<Cell<usize>>,
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    fn tracked<const N: usize>(drops: &Rc<Cell<usize>>) -> [Tracked; N] {
+        std::array::from_fn(|id| Tracked {
+            id,
+            drops: drops.clone(),
+        })
+    }
+
+    #[test]
+    fn it_holds_non_default_items() {
+        let drops = Rc::new(Cell::new(0));
+        let mut vec: LocalStorageVec<Tracked, 4> = LocalStorageVec::from(tracked::<3>(&drops));
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        let ids: Vec<_> = vec.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [0, 1, 2]);
+
+        assert_eq!(vec.pop().map(|t| t.id), Some(2));
+        assert_eq!(drops.get(), 1);
+        assert_eq!(vec.remove(0).id, 0);
+        assert_eq!(drops.get(), 2);
+        drop(vec);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_drops_once_after_spilling() {
+        let drops = Rc::new(Cell::new(0));
+        let mut vec: LocalStorageVec<Tracked, 2> = LocalStorageVec::new();
+        for t in tracked::<2>(&drops) {
+            vec.push(t);
+        }
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+        // Spill to the heap by pushing
+        vec.push(Tracked {
+            id: 2,
+            drops: drops.clone(),
+        });
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(drops.get(), 0);
+        drop(vec);
+        assert_eq!(drops.get(), 3);
+
+        // Spill to the heap by inserting
+        let drops = Rc::new(Cell::new(0));
+        let mut vec: LocalStorageVec<Tracked, 2> = LocalStorageVec::from(tracked::<2>(&drops));
+        vec.insert(
+            1,
+            Tracked {
+                id: 2,
+                drops: drops.clone(),
+            },
+        );
+        let ids: Vec<_> = vec.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [0, 2, 1]);
+        assert_eq!(drops.get(), 0);
+        drop(vec);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_drops_on_clear_and_insert() {
+        let drops = Rc::new(Cell::new(0));
+        let mut vec: LocalStorageVec<Tracked, 8> = LocalStorageVec::from(tracked::<4>(&drops));
+        vec.insert(
+            2,
+            Tracked {
+                id: 4,
+                drops: drops.clone(),
+            },
+        );
+        let ids: Vec<_> = vec.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [0, 1, 4, 2, 3]);
+        vec.clear();
+        assert_eq!(drops.get(), 5);
+        assert!(vec.is_empty());
+        drop(vec);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_drops_remaining_items_of_iter() {
+        let drops = Rc::new(Cell::new(0));
+        let vec: LocalStorageVec<Tracked, 8> = LocalStorageVec::from(tracked::<5>(&drops));
+        let mut iter = vec.into_iter();
+        assert_eq!(iter.next().map(|t| t.id), Some(0));
+        assert_eq!(iter.next_back().map(|t| t.id), Some(4));
+        assert_eq!(drops.get(), 2);
+        drop(iter);
+        assert_eq!(drops.get(), 5);
+
+        let drops = Rc::new(Cell::new(0));
+        let vec: LocalStorageVec<Tracked, 2> = LocalStorageVec::from(tracked::<5>(&drops));
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        let mut iter = vec.into_iter();
+        assert_eq!(iter.len(), 5);
+        assert_eq!(iter.next().map(|t| t.id), Some(0));
+        drop(iter);
+        assert_eq!(drops.get(), 5);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_truncates_and_resizes() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3]);
+        vec.truncate(2);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(vec.as_ref(), &[0, 1]);
+        vec.truncate(3);
+        assert_eq!(vec.as_ref(), &[0, 1]);
+
+        vec.resize(4, 7);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 1, 7, 7]);
+        vec.resize(6, 8);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[0, 1, 7, 7, 8, 8]);
+        vec.resize(1, 9);
+        assert_eq!(vec.as_ref(), &[0]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_extends() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::new();
+        vec.extend_from_slice(&[0, 1]);
+        vec.extend([2, 3]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        vec.extend(&[4]);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3, 4]);
+
+        let vec: LocalStorageVec<_, 4> = (0..4).collect();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        let vec: LocalStorageVec<_, 4> = (0..5).collect();
+        assert!(matches!(vec, LocalStorageVec::Heap(ref v) if v.len() == 5));
+        // The size hint of a `filter` has a lower bound of 0, yet the items fit on the stack
+        let vec: LocalStorageVec<_, 4> = (0..8).filter(|i| i % 2 == 0).collect();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 2, 4, 6]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_retains() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        vec.retain(|i| i % 2 == 0);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[0, 2, 4]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        vec.retain_mut(|i| {
+            *i *= 10;
+            *i > 20
+        });
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[30, 40, 50]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_dedups() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([1, 1, 2, 3, 3, 3, 1]);
+        vec.dedup();
+        assert_eq!(vec.as_ref(), &[1, 2, 3, 1]);
+
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([10, 11, 20, 21, 22, 30]);
+        vec.dedup_by_key(|i| *i / 10);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), &[10, 20, 30]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([10, 11, 20, 21, 22, 30]);
+        vec.dedup_by_key(|i| *i / 10);
+        assert_eq!(vec.as_ref(), &[10, 20, 30]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_drains() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        let drained: Vec<_> = vec.drain(1..3).collect();
+        assert_eq!(drained, [1, 2]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 3, 4, 5]);
+
+        // Items that are not consumed are removed as well
+        let mut drain = vec.drain(..=2);
+        assert_eq!(drain.next_back(), Some(4));
+        drop(drain);
+        assert_eq!(vec.as_ref(), &[5]);
+
+        let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        let drained: Vec<_> = vec.drain(4..).collect();
+        assert_eq!(drained, [4, 5]);
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3]);
+        vec.drain(..);
+        assert!(vec.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_panics_on_out_of_bounds_drain() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2]);
+        vec.drain(2..4);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_splices() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 2, 3]);
+        let removed: Vec<_> = vec.splice(1..3, [7, 8, 9]).collect();
+        assert_eq!(removed, [1, 2]);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 5, .. }));
+        assert_eq!(vec.as_ref(), &[0, 7, 8, 9, 3]);
+
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3]);
+        let removed: Vec<_> = vec.splice(..1, [7, 8]).collect();
+        assert_eq!(removed, [0]);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), &[7, 8, 1, 2, 3]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_splits_off_and_appends() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        let mut tail = vec.split_off(2);
+        assert_eq!(vec.as_ref(), &[0, 1]);
+        assert!(matches!(tail, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(tail.as_ref(), &[2, 3, 4, 5]);
+
+        let mut other: LocalStorageVec<_, 2> = LocalStorageVec::from([6, 7, 8]);
+        tail.append(&mut other);
+        assert!(other.is_empty());
+        assert!(matches!(tail, LocalStorageVec::Heap(_)));
+        assert_eq!(tail.as_ref(), &[2, 3, 4, 5, 6, 7, 8]);
+
+        let tail = tail.split_off(7);
+        assert!(matches!(tail, LocalStorageVec::Stack { len: 0, .. }));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_drops_drained_and_spliced_items_once() {
+        let drops = Rc::new(Cell::new(0));
+        let mut vec: LocalStorageVec<Tracked, 8> = LocalStorageVec::from(tracked::<6>(&drops));
+        let mut drain = vec.drain(1..4);
+        assert_eq!(drain.next().map(|t| t.id), Some(1));
+        drop(drain);
+        assert_eq!(drops.get(), 3);
+        let ids: Vec<_> = vec.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [0, 4, 5]);
+        drop(vec);
+        assert_eq!(drops.get(), 6);
+
+        // A leaked `Drain` leaks the items, but never drops them twice. These items borrow
+        // their counter instead of sharing an `Rc`, which Miri would report as leaked.
+        struct Counted<'a>(&'a Cell<usize>);
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
             }
         }
+        let drops = Cell::new(0);
+        let mut vec: LocalStorageVec<Counted, 8> = (0..3).map(|_| Counted(&drops)).collect();
+        std::mem::forget(vec.drain(1..));
+        assert_eq!(vec.len(), 1);
+        drop(vec);
+        assert_eq!(drops.get(), 1);
+
+        let drops = Rc::new(Cell::new(0));
+        let mut vec: LocalStorageVec<Tracked, 4> = LocalStorageVec::from(tracked::<4>(&drops));
+        let removed = vec.splice(2.., tracked::<3>(&drops));
+        assert_eq!(removed.len(), 2);
+        drop(removed);
+        assert_eq!(drops.get(), 2);
+        let ids: Vec<_> = vec.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [0, 1, 0, 1, 2]);
+        vec.retain(|t| t.id != 1);
+        assert_eq!(drops.get(), 4);
+        drop(vec);
+        assert_eq!(drops.get(), 7);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_shrinks_to_inline() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        vec.shrink_to_inline();
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        vec.truncate(4);
+        // Without a shrink policy, the items stay on the heap until asked otherwise
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        vec.shrink_to_inline();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 4, .. }));
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3]);
+
+        let drops = Rc::new(Cell::new(0));
+        let mut vec: LocalStorageVec<Tracked, 2> = LocalStorageVec::from(tracked::<3>(&drops));
+        assert_eq!(vec.pop().map(|t| t.id), Some(2));
+        vec.shrink_to_inline();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(drops.get(), 1);
+        drop(vec);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_shrinks_according_to_policy() {
+        let mut vec: LocalStorageVec<_, 4, 3> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        assert_eq!(vec.pop(), Some(5));
+        assert_eq!(vec.remove(0), 0);
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.pop(), Some(4));
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.pop(), Some(3));
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(vec.as_ref(), &[1, 2]);
+
+        let mut vec: LocalStorageVec<_, 4, 3> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        vec.drain(1..5);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+        assert_eq!(vec.as_ref(), &[0, 5]);
+
+        let mut vec: LocalStorageVec<_, 4, 3> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        vec.retain(|i| i % 3 == 0);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+
+        let mut vec: LocalStorageVec<_, 4, 3> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
+        vec.clear();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 0, .. }));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_does_not_thrash_at_the_boundaries() {
+        let mut vec: LocalStorageVec<_, 4, 2> = LocalStorageVec::from([0, 1, 2, 3, 4]);
+        let heap_ptr = vec.as_ptr();
+        // Hovering around `N` items keeps using the same heap allocation
+        for i in 0..100 {
+            assert_eq!(vec.pop(), Some(4));
+            vec.push(4);
+            assert!(matches!(vec, LocalStorageVec::Heap(_)));
+            assert_eq!(vec.as_ptr(), heap_ptr, "reallocated in iteration {i}");
+        }
+
+        vec.truncate(1);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 1, .. }));
+        // Hovering around `SHRINK_BELOW` items stays on the stack
+        for _ in 0..100 {
+            vec.push(1);
+            vec.push(2);
+            assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+            vec.pop();
+            vec.pop();
+            assert!(matches!(vec, LocalStorageVec::Stack { len: 1, .. }));
+        }
     }
 
-    // Uncomment me for part B
-    // #[test]
-    // fn it_from_vecs() {
-    //     // The `vec!` macro creates a `Vec<T>` in a way that resembles
-    //     // array-initialization syntax.
-    //
-    //     let vec: LocalStorageVec<usize, 10> = LocalStorageVec::from(vec![1, 2, 3]);
-    //     // Assert that the call to `from` indeed yields a `Stack` variant
-    //     assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: _ }));
-    //
-    //     let vec: LocalStorageVec<usize, 2> = LocalStorageVec::from(vec![1, 2, 3]);
-    //     // Assert that the call to `from` indeed yields a `Heap` variant
-    //     assert!(matches!(vec, LocalStorageVec::Heap(_)));
-    // }
-
-    // Uncomment me for part C
-    // #[test]
-    // fn it_as_refs() {
-    //     let vec: LocalStorageVec<i32, 256> = LocalStorageVec::from([0; 128]);
-    //     let slice: &[i32] = vec.as_ref();
-    //     assert!(slice.len() == 128);
-    //     let vec: LocalStorageVec<i32, 32> = LocalStorageVec::from([0; 128]);
-    //     let slice: &[i32] = vec.as_ref();
-    //     assert!(slice.len() == 128);
-    //
-    //     let mut vec: LocalStorageVec<i32, 256> = LocalStorageVec::from([0; 128]);
-    //     let slice_mut: &[i32] = vec.as_mut();
-    //     assert!(slice_mut.len() == 128);
-    //     let mut vec: LocalStorageVec<i32, 32> = LocalStorageVec::from([0; 128]);
-    //     let slice_mut: &[i32] = vec.as_mut();
-    //     assert!(slice_mut.len() == 128);
-    // }
-
-    // Uncomment me for part D
-    // #[test]
-    // fn it_constructs() {
-    //     let vec: LocalStorageVec<usize, 10> = LocalStorageVec::new();
-    //     // Assert that the call to `new` indeed yields a `Stack` variant with zero length
-    //     assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: 0 }));
-    // }
-
-    // Uncomment me for part D
-    // #[test]
-    // fn it_lens() {
-    //     let vec: LocalStorageVec<_, 3> = LocalStorageVec::from([0, 1, 2]);
-    //     assert_eq!(vec.len(), 3);
-    //     let vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
-    //     assert_eq!(vec.len(), 3);
-    // }
-
-    // Uncomment me for part D
-    // #[test]
-    // fn it_pushes() {
-    //     let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::new();
-    //     for value in 0..128 {
-    //         vec.push(value);
-    //     }
-    //     assert!(matches!(vec, LocalStorageVec::Stack { len: 128, .. }));
-    //     for value in 128..256 {
-    //         vec.push(value);
-    //     }
-    //     assert!(matches!(vec, LocalStorageVec::Heap(v) if v.len() == 256))
-    // }
-
-    // Uncomment me for part D
-    // #[test]
-    // fn it_pops() {
-    //     let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
-    //     for _ in 0..128 {
-    //         assert_eq!(vec.pop(), Some(0))
-    //     }
-    //     assert_eq!(vec.pop(), None);
-    //
-    //     let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 256]);
-    //     for _ in 0..256 {
-    //         assert_eq!(vec.pop(), Some(0))
-    //     }
-    //     assert_eq!(vec.pop(), None);
-    //
-    //     let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from(vec![0; 256]);
-    //     for _ in 0..256 {
-    //         assert_eq!(vec.pop(), Some(0))
-    //     }
-    //     assert_eq!(vec.pop(), None);
-    // }
-
-    // Uncomment me for part D
-    // #[test]
-    // fn it_inserts() {
-    //     let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
-    //     vec.insert(1, 3);
-    //     assert!(matches!(
-    //         vec,
-    //         LocalStorageVec::Stack {
-    //             buf: [0, 3, 1, 2],
-    //             len: 4
-    //         }
-    //     ));
-    //
-    //     let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3]);
-    //     vec.insert(1, 3);
-    //     assert!(matches!(vec, LocalStorageVec::Heap { .. }));
-    //     assert_eq!(vec.as_ref(), &[0, 3, 1, 2, 3]);
-    //
-    //     let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2, 3, 4]);
-    //     vec.insert(1, 3);
-    //     assert!(matches!(vec, LocalStorageVec::Heap { .. }));
-    //     assert_eq!(vec.as_ref(), &[0, 3, 1, 2, 3, 4])
-    // }
-
-    // Uncomment me for part D
-    // #[test]
-    // fn it_removes() {
-    //     let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([0, 1, 2]);
-    //     let elem = vec.remove(1);
-    //     dbg!(&vec);
-    //     assert!(matches!(
-    //         vec,
-    //         LocalStorageVec::Stack {
-    //             buf: [0, 2, _, _],
-    //             len: 2
-    //         }
-    //     ));
-    //     assert_eq!(elem, 1);
-    //
-    //     let mut vec: LocalStorageVec<_, 2> = LocalStorageVec::from([0, 1, 2]);
-    //     let elem = vec.remove(1);
-    //     assert!(matches!(vec, LocalStorageVec::Heap(..)));
-    //     assert_eq!(vec.as_ref(), &[0, 2]);
-    //     assert_eq!(elem, 1);
-    // }
-
-    // Uncomment me for part D
-    // #[test]
-    // fn it_clears() {
-    //     let mut vec: LocalStorageVec<_, 10> = LocalStorageVec::from([0, 1, 2, 3]);
-    //     assert!(matches!(vec, LocalStorageVec::Stack { buf: _, len: 4 }));
-    //     vec.clear();
-    //     assert_eq!(vec.len(), 0);
-    //
-    //     let mut vec: LocalStorageVec<_, 3> = LocalStorageVec::from([0, 1, 2, 3]);
-    //     assert!(matches!(vec, LocalStorageVec::Heap(_)));
-    //     vec.clear();
-    //     assert_eq!(vec.len(), 0);
-    // }
-
-    // Uncomment me for part E
-    // #[test]
-    // fn it_iters() {
-    //     let vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 32]);
-    //     let mut iter = vec.into_iter();
-    //     for item in &mut iter {
-    //         assert_eq!(item, 0);
-    //     }
-    //     assert_eq!(iter.next(), None);
-    //
-    //     let vec: LocalStorageVec<_, 128> = LocalStorageVec::from(vec![0; 128]);
-    //     let mut iter = vec.into_iter();
-    //     for item in &mut iter {
-    //         assert_eq!(item, 0);
-    //     }
-    //     assert_eq!(iter.next(), None);
-    // }
-
-    // Uncomment me for part F
-    // #[test]
-    // fn it_indexes() {
-    //     let vec: LocalStorageVec<i32, 10> = LocalStorageVec::from([0, 1, 2, 3, 4, 5]);
-    //     assert_eq!(vec[1], 1);
-    //     assert_eq!(vec[..2], [0, 1]);
-    //     assert_eq!(vec[4..], [4, 5]);
-    //     assert_eq!(vec[1..3], [1, 2]);
-    // }
-
-    // Uncomment me for part H
-    // #[test]
-    // fn it_borrowing_iters() {
-    //     let vec: LocalStorageVec<String, 10> = LocalStorageVec::from([
-    //         "0".to_owned(),
-    //         "1".to_owned(),
-    //         "2".to_owned(),
-    //         "3".to_owned(),
-    //         "4".to_owned(),
-    //         "5".to_owned(),
-    //     ]);
-    //     let iter = vec.iter();
-    //     for _ in iter {}
-    //     // This requires the `vec` not to be consumed by the call to `iter()`
-    //     drop(vec);
-    // }
-
-    // Uncomment me for part J
-    // #[test]
-    // fn it_derefs() {
-    //     use std::ops::{Deref, DerefMut};
-    //     let vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
-    //     // `chunks` is a method that's defined for slices `[T]`, that we can use thanks to `Deref`
-    //     let chunks = vec.chunks(4);
-    //     let slice: &[_] = vec.deref();
-    //
-    //     let mut vec: LocalStorageVec<_, 128> = LocalStorageVec::from([0; 128]);
-    //     let chunks = vec.chunks_mut(4);
-    //     let slice: &mut [_] = vec.deref_mut();
-    // }
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    fn it_has_a_fixed_capacity_without_alloc() {
+        let mut vec: LocalStorageVec<_, 3> = LocalStorageVec::new();
+        assert_eq!(vec.capacity(), 3);
+        assert_eq!(vec.push(0), Ok(()));
+        assert_eq!(vec.push(1), Ok(()));
+        assert_eq!(vec.insert(0, 2), Ok(()));
+        assert_eq!(vec.push(3), Err(3));
+        assert_eq!(vec.insert(1, 4), Err(4));
+        assert_eq!(vec.as_ref(), &[2, 0, 1]);
+
+        assert_eq!(vec.remove(0), 2);
+        assert_eq!(vec.insert(1, 4), Ok(()));
+        assert_eq!(vec.as_ref(), &[0, 4, 1]);
+        assert_eq!(vec.pop(), Some(1));
+        vec.clear();
+        assert_eq!(vec.pop(), None);
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    fn it_hands_back_items_that_do_not_fit() {
+        let drops = Rc::new(Cell::new(0));
+        let mut vec: LocalStorageVec<Tracked, 2> = LocalStorageVec::from(tracked::<2>(&drops));
+        let rejected = vec.push(Tracked {
+            id: 2,
+            drops: drops.clone(),
+        });
+        assert_eq!(rejected.as_ref().map_err(|t| t.id), Err(2));
+        assert_eq!(drops.get(), 0);
+        drop(rejected);
+        assert_eq!(drops.get(), 1);
+        drop(vec);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    fn it_mutates_inline_without_alloc() {
+        let mut vec: LocalStorageVec<_, 8> = LocalStorageVec::from([0, 1, 1, 2]);
+        vec.extend_from_slice(&[3, 3]);
+        vec.dedup();
+        assert_eq!(vec.as_ref(), &[0, 1, 2, 3]);
+        vec.retain(|i| i % 2 == 1);
+        assert_eq!(vec.as_ref(), &[1, 3]);
+        vec.resize(4, 5);
+        assert_eq!(vec.as_ref(), &[1, 3, 5, 5]);
+
+        let drained: Vec<_> = vec.drain(1..3).collect();
+        assert_eq!(drained, [3, 5]);
+        let removed: Vec<_> = vec.splice(..1, [6, 7, 8]).collect();
+        assert_eq!(removed, [1]);
+        assert_eq!(vec.as_ref(), &[6, 7, 8, 5]);
+
+        let mut tail = vec.split_off(2);
+        assert_eq!(tail.as_ref(), &[8, 5]);
+        tail.append(&mut vec);
+        assert_eq!(tail.as_ref(), &[8, 5, 6, 7]);
+        tail.truncate(1);
+        assert_eq!(tail.as_ref(), &[8]);
+
+        let collected: LocalStorageVec<_, 8> = (0..8).collect();
+        assert_eq!(collected.len(), 8);
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    #[should_panic = "capacity of 4 exceeded"]
+    fn it_panics_when_extending_beyond_capacity_without_alloc() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::new();
+        vec.extend(0..5);
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    #[should_panic = "capacity of 4 exceeded"]
+    fn it_panics_when_reserving_too_much_without_alloc() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([1, 2]);
+        vec.reserve(2);
+        assert_eq!(vec.capacity(), 4);
+        vec.reserve(usize::MAX);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    #[should_panic = "capacity overflow"]
+    fn it_panics_when_reserving_too_much() {
+        let mut vec: LocalStorageVec<_, 4> = LocalStorageVec::from([1, 2]);
+        vec.reserve(2);
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 2, .. }));
+        vec.reserve(usize::MAX);
+    }
 }
@@ -0,0 +1,135 @@
+//! `Serialize` and `Deserialize` implementations, enabled by the `serde` feature.
+//! A `LocalStorageVec` is represented as a sequence, just like a `Vec`.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::LocalStorageVec;
+
+impl<T: Serialize, const N: usize, const S: usize> Serialize for LocalStorageVec<T, N, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Deserialize<'de>, const N: usize, const S: usize> Deserialize<'de>
+    for LocalStorageVec<T, N, S>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(LocalStorageVecVisitor(PhantomData))
+    }
+}
+
+struct LocalStorageVecVisitor<T, const N: usize, const S: usize>(
+    PhantomData<LocalStorageVec<T, N, S>>,
+);
+
+impl<'de, T: Deserialize<'de>, const N: usize, const S: usize> Visitor<'de>
+    for LocalStorageVecVisitor<T, N, S>
+{
+    type Value = LocalStorageVec<T, N, S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if cfg!(feature = "alloc") {
+            formatter.write_str("a sequence")
+        } else {
+            write!(formatter, "a sequence of at most {N} items")
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut vec = LocalStorageVec::new();
+        // If the length is known up front and the items don't fit on the stack,
+        // allocate once. The length comes from the input, so we don't trust it
+        // blindly: the rest is allocated while pushing, if the items do show up.
+        if let Some(len) = seq.size_hint() {
+            vec.reserve(len.min(MAX_PREALLOC_BYTES / core::mem::size_of::<T>().max(1)));
+        }
+        while let Some(item) = seq.next_element()? {
+            vec.push(item);
+        }
+        Ok(vec)
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut vec = LocalStorageVec::new();
+        while let Some(item) = seq.next_element()? {
+            if vec.push(item).is_err() {
+                return Err(serde::de::Error::invalid_length(N + 1, &self));
+            }
+        }
+        Ok(vec)
+    }
+}
+
+#[cfg(feature = "alloc")]
+const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+
+#[cfg(test)]
+mod test {
+    use crate::LocalStorageVec;
+    use serde_test::{assert_tokens, Token};
+    use std::prelude::rust_2021::*;
+    use std::vec;
+
+    #[test]
+    fn it_serializes_like_a_vec() {
+        let vec: LocalStorageVec<u32, 4> = LocalStorageVec::from([1, 2, 3]);
+        assert_tokens(
+            &vec,
+            &[
+                Token::Seq { len: Some(3) },
+                Token::U32(1),
+                Token::U32(2),
+                Token::U32(3),
+                Token::SeqEnd,
+            ],
+        );
+
+        let vec: LocalStorageVec<u32, 3> = LocalStorageVec::from([1, 2, 3]);
+        assert_eq!(
+            serde_json::to_string(&vec).unwrap(),
+            serde_json::to_string(&vec![1, 2, 3]).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_deserializes_inline_if_it_fits() {
+        let vec: LocalStorageVec<String, 3> = serde_json::from_str(r#"["a", "b", "c"]"#).unwrap();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 3, .. }));
+        assert_eq!(vec.as_ref(), ["a", "b", "c"]);
+
+        let vec: LocalStorageVec<String, 3> = serde_json::from_str("[]").unwrap();
+        assert!(matches!(vec, LocalStorageVec::Stack { len: 0, .. }));
+
+        let vec: LocalStorageVec<String, 3> =
+            serde_json::from_str(r#"["a", "b", "c", "d"]"#).unwrap();
+        assert!(matches!(vec, LocalStorageVec::Heap(_)));
+        assert_eq!(vec.as_ref(), ["a", "b", "c", "d"]);
+        assert_eq!(serde_json::to_string(&vec).unwrap(), r#"["a","b","c","d"]"#);
+    }
+
+    #[test]
+    fn it_reports_invalid_items() {
+        let result: Result<LocalStorageVec<u8, 3>, _> = serde_json::from_str("[1, 256]");
+        assert!(result.is_err());
+        let result: Result<LocalStorageVec<u8, 3>, _> = serde_json::from_str(r#"{"a": 1}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    fn it_rejects_too_many_items_without_alloc() {
+        let vec: LocalStorageVec<u32, 3> = serde_json::from_str("[1, 2, 3]").unwrap();
+        assert_eq!(vec.as_ref(), [1, 2, 3]);
+
+        let err = serde_json::from_str::<LocalStorageVec<u32, 3>>("[1, 2, 3, 4]").unwrap_err();
+        assert!(err.to_string().contains("at most 3 items"), "{err}");
+    }
+}
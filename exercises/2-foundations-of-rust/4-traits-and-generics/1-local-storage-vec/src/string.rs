@@ -0,0 +1,342 @@
+//! A UTF-8 string that keeps short contents on the stack, built on top of
+//! a `LocalStorageVec<u8, N>`.
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use core::ops::{Deref, DerefMut};
+use core::{cmp, fmt, str};
+
+use crate::LocalStorageVec;
+
+/// A growable string that resides on the stack if it's at most `N` bytes
+/// long, and is moved to the heap to grow larger if needed.
+///
+/// Hashing, comparing and ordering a `LocalStorageString` gives the same
+/// results as doing so with the `str` it contains.
+#[derive(Clone, Default)]
+pub struct LocalStorageString<const N: usize> {
+    /// Always contains valid UTF-8
+    vec: LocalStorageVec<u8, N>,
+}
+
+impl<const N: usize> LocalStorageString<N> {
+    /// Creates a new, empty `LocalStorageString` without allocating
+    pub const fn new() -> Self {
+        Self {
+            vec: LocalStorageVec::new(),
+        }
+    }
+
+    /// The length of the string in bytes
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// The number of bytes this `LocalStorageString` can hold without (re)allocating
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    pub fn as_str(&self) -> &str {
+        // SAFETY: `vec` always contains valid UTF-8
+        unsafe { str::from_utf8_unchecked(&self.vec) }
+    }
+
+    pub fn as_mut_str(&mut self) -> &mut str {
+        // SAFETY: `vec` always contains valid UTF-8, and `str` only allows
+        // mutations that keep it that way
+        unsafe { str::from_utf8_unchecked_mut(&mut self.vec) }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.vec
+    }
+
+    /// Converts the `LocalStorageString` into its underlying bytes
+    pub fn into_bytes(self) -> LocalStorageVec<u8, N> {
+        self.vec
+    }
+
+    /// Appends a string slice, moving the contents to the heap if they no longer fit on the stack
+    #[cfg(feature = "alloc")]
+    pub fn push_str(&mut self, string: &str) {
+        self.vec.extend_from_slice(string.as_bytes());
+    }
+
+    /// Appends a string slice, or hands it back in an `Err` if it doesn't fit.
+    /// Either all of `string` is appended, or none of it.
+    #[cfg(not(feature = "alloc"))]
+    pub fn push_str<'a>(&mut self, string: &'a str) -> Result<(), &'a str> {
+        if self.len() + string.len() > N {
+            return Err(string);
+        }
+        self.vec.extend_from_slice(string.as_bytes());
+        Ok(())
+    }
+
+    /// Appends a `char`, moving the contents to the heap if they no longer fit on the stack
+    #[cfg(feature = "alloc")]
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Appends a `char`, or hands it back in an `Err` if it doesn't fit
+    #[cfg(not(feature = "alloc"))]
+    pub fn push(&mut self, ch: char) -> Result<(), char> {
+        self.push_str(ch.encode_utf8(&mut [0; 4])).map_err(|_| ch)
+    }
+
+    /// Removes the last `char` and returns it, or `None` if the string is empty
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.chars().next_back()?;
+        self.vec.truncate(self.len() - ch.len_utf8());
+        Some(ch)
+    }
+
+    /// Shortens the string to `new_len` bytes. Has no effect if `new_len`
+    /// is greater than the current length.
+    ///
+    /// # Panics
+    /// Panics if `new_len` does not lie on a `char` boundary
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            assert!(
+                self.is_char_boundary(new_len),
+                "new_len (is {new_len}) does not lie on a char boundary"
+            );
+            self.vec.truncate(new_len);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vec.clear();
+    }
+}
+
+/// Without the `alloc` feature, this panics if `string` is longer than `N` bytes
+impl<const N: usize> From<&str> for LocalStorageString<N> {
+    fn from(string: &str) -> Self {
+        let mut vec = LocalStorageVec::new();
+        vec.extend_from_slice(string.as_bytes());
+        Self { vec }
+    }
+}
+
+/// Reuses the allocation of `string` if it doesn't fit on the stack
+#[cfg(feature = "alloc")]
+impl<const N: usize> From<String> for LocalStorageString<N> {
+    fn from(string: String) -> Self {
+        Self {
+            vec: LocalStorageVec::from(string.into_bytes()),
+        }
+    }
+}
+
+impl<const N: usize> Deref for LocalStorageString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> DerefMut for LocalStorageString<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for LocalStorageString<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for LocalStorageString<N> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<const N: usize> Borrow<str> for LocalStorageString<N> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Display for LocalStorageString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> fmt::Debug for LocalStorageString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Without the `alloc` feature, writing fails if the result doesn't fit
+impl<const N: usize> fmt::Write for LocalStorageString<N> {
+    #[cfg(feature = "alloc")]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s).map_err(|_| fmt::Error)
+    }
+}
+
+impl<const N: usize> Hash for LocalStorageString<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl<const N: usize, const M: usize> PartialEq<LocalStorageString<M>> for LocalStorageString<N> {
+    fn eq(&self, other: &LocalStorageString<M>) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const N: usize> Eq for LocalStorageString<N> {}
+
+impl<const N: usize> PartialEq<str> for LocalStorageString<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const N: usize> PartialEq<&str> for LocalStorageString<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const N: usize, const M: usize> PartialOrd<LocalStorageString<M>> for LocalStorageString<N> {
+    fn partial_cmp(&self, other: &LocalStorageString<M>) -> Option<cmp::Ordering> {
+        self.as_str().partial_cmp(other.as_str())
+    }
+}
+
+impl<const N: usize> Ord for LocalStorageString<N> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LocalStorageString;
+    use crate::LocalStorageVec;
+    use core::fmt::Write;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+    use std::prelude::rust_2021::*;
+
+    fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn it_derefs_to_str() {
+        let s: LocalStorageString<8> = LocalStorageString::from("héllo");
+        assert_eq!(s.len(), 6);
+        assert!(s.starts_with("hé"));
+        assert_eq!(s.to_uppercase(), "HÉLLO");
+        assert_eq!(format!("{s}"), "héllo");
+        assert_eq!(format!("{s:?}"), "\"héllo\"");
+        assert_eq!(format!("{s:>7}"), "  héllo");
+
+        let mut s: LocalStorageString<8> = LocalStorageString::from("abc");
+        s.make_ascii_uppercase();
+        assert_eq!(s, "ABC");
+    }
+
+    #[test]
+    fn it_pushes_and_pops_chars() {
+        let mut s: LocalStorageString<8> = LocalStorageString::new();
+        assert!(s.is_empty());
+        assert_eq!(s.pop(), None);
+        for ch in ['a', 'ß', '🦀'] {
+            write!(s, "{ch}").unwrap();
+        }
+        assert_eq!(s.len(), 7);
+        assert_eq!(s.pop(), Some('🦀'));
+        assert_eq!(s.pop(), Some('ß'));
+        assert_eq!(s.as_str(), "a");
+    }
+
+    #[test]
+    #[should_panic = "char boundary"]
+    fn it_refuses_to_truncate_in_the_middle_of_a_char() {
+        let mut s: LocalStorageString<8> = LocalStorageString::from("ß");
+        s.truncate(1);
+    }
+
+    #[test]
+    fn it_hashes_and_compares_like_str() {
+        let a: LocalStorageString<8> = LocalStorageString::from("apple");
+        let b: LocalStorageString<16> = LocalStorageString::from("banana");
+        assert_eq!(hash_of(&a), hash_of("apple"));
+        assert_eq!(a, "apple");
+        assert!(a < b);
+        assert_eq!(
+            a.cmp(&LocalStorageString::from("apricot")),
+            "apple".cmp("apricot")
+        );
+
+        // Thanks to `Borrow<str>`, we can look up entries using a `&str`
+        let set: HashSet<LocalStorageString<8>> = ["apple", "pear"]
+            .into_iter()
+            .map(LocalStorageString::from)
+            .collect();
+        assert!(set.contains("pear"));
+        assert!(!set.contains("banana"));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn it_spills_to_the_heap() {
+        let mut s: LocalStorageString<4> = LocalStorageString::new();
+        s.push_str("abcd");
+        assert!(matches!(s.vec, LocalStorageVec::Stack { len: 4, .. }));
+        s.push('é');
+        assert!(matches!(s.vec, LocalStorageVec::Heap(_)));
+        write!(s, "-{}", 42).unwrap();
+        assert_eq!(s, "abcdé-42");
+
+        let s: LocalStorageString<4> = LocalStorageString::from(String::from("abc"));
+        assert!(matches!(s.vec, LocalStorageVec::Stack { len: 3, .. }));
+        let string = String::from("abcdefgh");
+        let ptr = string.as_ptr();
+        let s: LocalStorageString<4> = LocalStorageString::from(string);
+        // The allocation of the `String` is reused
+        assert_eq!(s.as_ptr(), ptr);
+    }
+
+    #[test]
+    #[cfg(not(feature = "alloc"))]
+    fn it_hands_back_what_does_not_fit_without_alloc() {
+        let mut s: LocalStorageString<4> = LocalStorageString::new();
+        assert_eq!(s.push_str("abc"), Ok(()));
+        assert_eq!(s.push('é'), Err('é'));
+        assert_eq!(s.push_str("de"), Err("de"));
+        assert_eq!(s.push('d'), Ok(()));
+        assert!(write!(s, "e").is_err());
+        assert_eq!(s, "abcd");
+        assert!(matches!(s.vec, LocalStorageVec::Stack { len: 4, .. }));
+    }
+}